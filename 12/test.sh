@@ -1,8 +1,8 @@
 #!/bin/bash
-BASE=/Users/ozaki/dev/nand2tetris
-PROJECT="${BASE}/projects"
+PROJECT=$(cd "$(dirname "$0")/.." && pwd)
 COMPILER="${PROJECT}/jackc/target/release/jackc"
-VM_EMULATOR="${BASE}/tools/VMEmulator.sh"
+# OS classes not found in the target directory are implemented natively by vmemulator
+TST_RUNNER="${PROJECT}/tstrunner/target/release/tstrunner"
 
 compile() {
   local name=$1
@@ -14,7 +14,6 @@ compile() {
 
   rm -rf ${target}
   mkdir -p ${target}
-  cp ${BASE}/tools/OS/*.vm ${target}
   cp ${PROJECT}/12/${name}Test/* ${target}
   cp "${PROJECT}/12/${name}.jack" ${target}
 
@@ -50,7 +49,6 @@ run_test() {
 
   rm -rf ${target}
   mkdir -p ${target}
-  cp ${BASE}/tools/OS/*.vm ${target}
   cp ${PROJECT}/12/${name}Test/* ${target}
   cp "${PROJECT}/12/${name}.jack" ${target}
 
@@ -66,7 +64,7 @@ run_test() {
 
   echo "Run Test ${target}/${name}Test.tst"
   local res="Pass"
-  test_out=$($VM_EMULATOR "${target}/${name}Test.tst" 2>&1)
+  test_out=$($TST_RUNNER "${target}/${name}Test.tst" 2>&1)
 
   if [ $? -ne 0 ]; then
     res="Fail"
@@ -0,0 +1,133 @@
+use super::instruction::*;
+use super::memory::{Memory, Rom};
+
+use anyhow::Result;
+
+// Hack CPU with A/D/PC registers, instruction memory and data memory
+#[derive(Debug, Clone)]
+pub struct Cpu {
+    pub a:    u16,
+    pub d:    u16,
+    pub pc:   u16,
+    pub time: usize, // number of executed instructions
+    pub rom:  Rom,
+    pub ram:  Memory,
+}
+
+impl Cpu {
+    pub fn new(rom: Rom) -> Cpu {
+        Cpu {
+            a: 0,
+            d: 0,
+            pc: 0,
+            time: 0,
+            rom,
+            ram: Memory::new(),
+        }
+    }
+
+    // reset registers, RAM is retained
+    pub fn reset(&mut self) {
+        self.a = 0;
+        self.d = 0;
+        self.pc = 0;
+        self.time = 0;
+    }
+
+    pub fn load(&mut self, rom: Rom) {
+        self.rom = rom;
+        self.reset();
+    }
+
+    pub fn current_instruction(&self) -> Instruction {
+        decode(self.rom.fetch(self.pc))
+    }
+
+    // execute one instruction
+    pub fn step(&mut self) -> Result<()> {
+        match self.current_instruction() {
+            Instruction::A(value) => {
+                self.a = value;
+                self.pc = next_addr(self.pc);
+            }
+            Instruction::C { comp, dest, jump } => {
+                let addr = self.a;
+                let y = if comp & COMP_A_BIT != 0 {
+                    self.ram.read(addr as usize)?
+                } else {
+                    self.a
+                };
+                let out = alu(comp, self.d, y);
+
+                if dest & DEST_M != 0 {
+                    self.ram.write(addr as usize, out)?;
+                }
+                if dest & DEST_A != 0 {
+                    self.a = out;
+                }
+                if dest & DEST_D != 0 {
+                    self.d = out;
+                }
+
+                // jump destination is the A-register value before this instruction
+                self.pc = if should_jump(jump, out) {
+                    addr & 0x7fff
+                } else {
+                    next_addr(self.pc)
+                };
+            }
+        }
+
+        self.time += 1;
+        Ok(())
+    }
+
+    // execute until `pred` returns true or `max_cycles` instructions were executed.
+    // returns the number of executed instructions
+    pub fn run_until<F>(&mut self, max_cycles: usize, mut pred: F) -> Result<usize>
+    where
+        F: FnMut(&Cpu) -> bool,
+    {
+        let mut cycles = 0;
+        while cycles < max_cycles && !pred(self) {
+            self.step()?;
+            cycles += 1;
+        }
+        Ok(cycles)
+    }
+
+    // execute until the program reaches its terminating infinite loop
+    pub fn run(&mut self, max_cycles: usize) -> Result<usize> {
+        self.run_until(max_cycles, |cpu| cpu.is_halted())
+    }
+
+    // true if the CPU is spinning in `(END) @END 0;JMP`
+    pub fn is_halted(&self) -> bool {
+        self.is_halt_loop(self.pc)
+            || (self.pc > 0 && self.a == self.pc - 1 && self.is_halt_loop(self.pc - 1))
+    }
+
+    fn is_halt_loop(&self, addr: u16) -> bool {
+        match (
+            decode(self.rom.fetch(addr)),
+            decode(self.rom.fetch(next_addr(addr))),
+        ) {
+            (Instruction::A(target), Instruction::C { comp, jump, .. }) => {
+                target == addr && is_unconditional_jump(comp, jump)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn read_ram(&self, addr: usize) -> Result<u16> {
+        self.ram.read(addr)
+    }
+
+    pub fn write_ram(&mut self, addr: usize, value: u16) -> Result<()> {
+        self.ram.write(addr, value)
+    }
+}
+
+fn next_addr(pc: u16) -> u16 {
+    (pc + 1) & 0x7fff
+}
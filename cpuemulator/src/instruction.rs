@@ -0,0 +1,79 @@
+// decoded hack instruction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    A(u16),
+    C { comp: u8, dest: u8, jump: u8 },
+}
+
+// dest bits
+pub const DEST_M: u8 = 0b001;
+pub const DEST_D: u8 = 0b010;
+pub const DEST_A: u8 = 0b100;
+
+// jump bits
+pub const JUMP_GT: u8 = 0b001;
+pub const JUMP_EQ: u8 = 0b010;
+pub const JUMP_LT: u8 = 0b100;
+
+// a-bit of comp field (use M instead of A)
+pub const COMP_A_BIT: u8 = 0b1000000;
+
+pub fn decode(word: u16) -> Instruction {
+    if word & 0x8000 == 0 {
+        Instruction::A(word)
+    } else {
+        Instruction::C {
+            comp: ((word >> 6) & 0b1111111) as u8,
+            dest: ((word >> 3) & 0b111) as u8,
+            jump: (word & 0b111) as u8,
+        }
+    }
+}
+
+pub fn encode(inst: Instruction) -> u16 {
+    match inst {
+        Instruction::A(value) => value & 0x7fff,
+        Instruction::C { comp, dest, jump } => {
+            0b111 << 13
+                | (comp as u16 & 0b1111111) << 6
+                | (dest as u16 & 0b111) << 3
+                | (jump as u16 & 0b111)
+        }
+    }
+}
+
+// compute ALU output from the 6 control bits (zx nx zy ny f no) of comp.
+// x is D-register, y is A-register or M
+pub fn alu(comp: u8, x: u16, y: u16) -> u16 {
+    let bit = |n: u8| comp & (1 << n) != 0;
+
+    let x = if bit(5) { 0 } else { x };
+    let x = if bit(4) { !x } else { x };
+    let y = if bit(3) { 0 } else { y };
+    let y = if bit(2) { !y } else { y };
+    let out = if bit(1) { x.wrapping_add(y) } else { x & y };
+
+    if bit(0) {
+        !out
+    } else {
+        out
+    }
+}
+
+// ALU output does not depend on D, A nor M (both zx and zy are set)
+pub fn is_constant_comp(comp: u8) -> bool {
+    comp & 0b0101000 == 0b0101000
+}
+
+pub fn should_jump(jump: u8, out: u16) -> bool {
+    let out = out as i16;
+
+    (jump & JUMP_LT != 0 && out < 0)
+        || (jump & JUMP_EQ != 0 && out == 0)
+        || (jump & JUMP_GT != 0 && out > 0)
+}
+
+// jump is always taken regardless of registers
+pub fn is_unconditional_jump(comp: u8, jump: u8) -> bool {
+    jump == 0b111 || (jump != 0 && is_constant_comp(comp) && should_jump(jump, alu(comp, 0, 0)))
+}
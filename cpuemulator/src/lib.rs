@@ -0,0 +1,8 @@
+pub mod cpu;
+pub mod instruction;
+pub mod loader;
+pub mod memory;
+
+pub use cpu::Cpu;
+pub use instruction::Instruction;
+pub use memory::{Memory, Rom};
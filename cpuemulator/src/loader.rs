@@ -0,0 +1,105 @@
+use super::instruction::{encode, Instruction};
+use super::memory::Rom;
+
+use hackasm::parser::{self, Node};
+use hackasm::symbols::Symbols;
+
+use anyhow::{anyhow, Result};
+
+use std::fs;
+use std::path::Path;
+
+// load `*.hack` or `*.asm` file into ROM
+pub fn load_file(path: &Path) -> Result<Rom> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| anyhow!("cannot read file: {} : {}", path.display(), err))?;
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("hack") => load_hack(&contents),
+        Some("asm") => load_asm(&contents),
+        _ => Err(anyhow!(
+            "invalid filename, exptected to '*.hack' or '*.asm': {}",
+            path.display()
+        )),
+    }
+}
+
+// load the `0/1` text generated by `hackasm::codegen::generate`
+pub fn load_hack(contents: &str) -> Result<Rom> {
+    Rom::new(parse_hack(contents)?)
+}
+
+// assemble hack asm and load it
+pub fn load_asm(contents: &str) -> Result<Rom> {
+    let mut symbols = Symbols::new();
+    let (nodes, errors) = parser::parse(contents, &mut symbols);
+
+    if !errors.is_empty() {
+        let messages = errors
+            .iter()
+            .map(|err| format!("  {}", err))
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Err(anyhow!("parse error: \n{}", messages));
+    }
+
+    let nodes = symbols.resolve(nodes);
+    load_nodes(&nodes)
+}
+
+// load resolved nodes from `hackasm::parser::parse`
+pub fn load_nodes(nodes: &[Node]) -> Result<Rom> {
+    Rom::new(encode_nodes(nodes)?)
+}
+
+pub fn parse_hack(contents: &str) -> Result<Vec<u16>> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(n, line)| (n + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(n, line)| {
+            parse_word(line).ok_or_else(|| anyhow!("line {} : invalid machine code: {}", n, line))
+        })
+        .collect()
+}
+
+fn parse_word(line: &str) -> Option<u16> {
+    if line.len() != 16 || !line.chars().all(|c| c == '0' || c == '1') {
+        return None;
+    }
+    u16::from_str_radix(line, 2).ok()
+}
+
+pub fn encode_nodes(nodes: &[Node]) -> Result<Vec<u16>> {
+    nodes.iter().filter_map(encode_node).collect()
+}
+
+fn encode_node(node: &Node) -> Option<Result<u16>> {
+    match node {
+        Node::A(a) => {
+            if a.value < 0 {
+                let name = a.symbol_name.clone().unwrap_or_default();
+                Some(Err(anyhow!(
+                    "ROM[{}] : unresolved symbol: {}",
+                    a.addr,
+                    name
+                )))
+            } else if a.value > 0x7fff {
+                Some(Err(anyhow!(
+                    "ROM[{}] : constant out of range: {}",
+                    a.addr,
+                    a.value
+                )))
+            } else {
+                Some(Ok(encode(Instruction::A(a.value as u16))))
+            }
+        }
+        Node::C(c) => Some(Ok(encode(Instruction::C {
+            comp: c.comp.mcode as u8,
+            dest: c.dest as u8,
+            jump: c.jump as u8,
+        }))),
+        Node::L(_) => None,
+    }
+}
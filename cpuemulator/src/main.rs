@@ -0,0 +1,145 @@
+extern crate cpuemulator;
+
+use cpuemulator::loader;
+use cpuemulator::Cpu;
+
+use getopts::Options;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+const DEFAULT_MAX_CYCLES: usize = 1_000_000;
+
+struct Config {
+    target:     String,
+    max_cycles: usize,
+    inits:      Vec<(usize, u16)>,
+    dumps:      Vec<(usize, usize)>,
+}
+
+/**
+ * 1. load *.hack (or assemble *.asm)
+ * 2. initialize RAM
+ * 3. run until the program halts or max cycles
+ * 4. dump registers and RAM
+ */
+fn main() {
+    let config = parse_args();
+
+    let rom = loader::load_file(Path::new(&config.target)).unwrap_or_else(|err| {
+        println!("cannot load program: {}", err);
+        process::exit(1);
+    });
+
+    let mut cpu = Cpu::new(rom);
+
+    config.inits.iter().for_each(|(addr, value)| {
+        cpu.write_ram(*addr, *value).unwrap_or_else(|err| {
+            println!("cannot set RAM: {}", err);
+            process::exit(1);
+        })
+    });
+
+    let cycles = cpu.run(config.max_cycles).unwrap_or_else(|err| {
+        println!("runtime error at PC={} : {}", cpu.pc, err);
+        process::exit(1);
+    });
+
+    if cpu.is_halted() {
+        println!("halted after {} cycles", cycles);
+    } else {
+        println!("stopped after {} cycles (max cycles reached)", cycles);
+    }
+    println!("PC={} A={} D={}", cpu.pc, cpu.a, cpu.d as i16);
+
+    config.dumps.iter().for_each(|(from, to)| {
+        (*from..=*to).for_each(|addr| match cpu.read_ram(addr) {
+            Ok(value) => println!("RAM[{}] = {}", addr, value as i16),
+            Err(err) => println!("{}", err),
+        })
+    });
+}
+
+fn parse_args() -> Config {
+    let args: Vec<String> = env::args().collect();
+    let mut opts = Options::new();
+
+    opts.optopt(
+        "c",
+        "cycles",
+        "max number of cycles to run (default 1000000)",
+        "N",
+    );
+    opts.optmulti("s", "set", "initialize RAM before running", "ADDR=VALUE");
+    opts.optmulti("d", "dump", "print RAM after running", "FROM[:TO]");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            process::exit(1);
+        }
+    };
+
+    let max_cycles = matches.opt_str("cycles").map_or(DEFAULT_MAX_CYCLES, |n| {
+        n.parse::<usize>().unwrap_or_else(|err| {
+            println!("invalid cycles: {}, {}", n, err);
+            process::exit(1);
+        })
+    });
+
+    let inits = matches
+        .opt_strs("set")
+        .iter()
+        .map(|s| parse_init(s))
+        .collect();
+    let dumps = matches
+        .opt_strs("dump")
+        .iter()
+        .map(|s| parse_range(s))
+        .collect();
+
+    let target = if !matches.free.is_empty() {
+        matches.free[0].clone()
+    } else {
+        println!(
+            "{}",
+            opts.usage("usage: cpuemulator [options] FILE.hack|FILE.asm")
+        );
+        process::exit(1);
+    };
+
+    Config {
+        target,
+        max_cycles,
+        inits,
+        dumps,
+    }
+}
+
+fn parse_init(s: &str) -> (usize, u16) {
+    let parsed = s.split_once('=').and_then(|(addr, value)| {
+        Some((
+            addr.parse::<usize>().ok()?,
+            value.parse::<i16>().ok()? as u16,
+        ))
+    });
+
+    parsed.unwrap_or_else(|| {
+        println!("invalid --set, expected ADDR=VALUE: {}", s);
+        process::exit(1);
+    })
+}
+
+fn parse_range(s: &str) -> (usize, usize) {
+    let (from, to) = s.split_once(':').unwrap_or((s, s));
+    let parsed = from
+        .parse::<usize>()
+        .and_then(|from| to.parse::<usize>().map(|to| (from, to)));
+
+    parsed.unwrap_or_else(|_| {
+        println!("invalid --dump, expected FROM[:TO]: {}", s);
+        process::exit(1);
+    })
+}
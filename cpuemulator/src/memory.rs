@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Result};
+
+pub const ROM_SIZE: usize = 32768;
+
+pub const SCREEN: usize = 16384;
+pub const SCREEN_SIZE: usize = 8192;
+pub const SCREEN_WIDTH: usize = 512;
+pub const SCREEN_HEIGHT: usize = 256;
+pub const KBD: usize = 24576;
+
+// RAM(16K) + SCREEN(8K) + KBD
+pub const MEMORY_SIZE: usize = KBD + 1;
+
+// instruction memory
+#[derive(Debug, Clone)]
+pub struct Rom {
+    words: Vec<u16>,
+    len:   usize,
+}
+
+impl Rom {
+    pub fn new(program: Vec<u16>) -> Result<Rom> {
+        let len = program.len();
+        if len > ROM_SIZE {
+            return Err(anyhow!(
+                "program too large : {} words, ROM holds {} words",
+                len,
+                ROM_SIZE
+            ));
+        }
+
+        let mut words = program;
+        words.resize(ROM_SIZE, 0);
+
+        Ok(Rom { words, len })
+    }
+
+    // number of words loaded from the program
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn fetch(&self, addr: u16) -> u16 {
+        self.words[addr as usize % ROM_SIZE]
+    }
+
+    pub fn words(&self) -> &[u16] {
+        &self.words[..self.len]
+    }
+}
+
+// data memory with SCREEN and KBD memory maps
+#[derive(Debug, Clone)]
+pub struct Memory {
+    words: Vec<u16>,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Memory::new()
+    }
+}
+
+impl Memory {
+    pub fn new() -> Memory {
+        Memory {
+            words: vec![0; MEMORY_SIZE],
+        }
+    }
+
+    pub fn read(&self, addr: usize) -> Result<u16> {
+        self.words
+            .get(addr)
+            .copied()
+            .ok_or_else(|| anyhow!("illegal memory access : read RAM[{}]", addr))
+    }
+
+    pub fn write(&mut self, addr: usize, value: u16) -> Result<()> {
+        let word = self
+            .words
+            .get_mut(addr)
+            .ok_or_else(|| anyhow!("illegal memory access : write RAM[{}]", addr))?;
+        *word = value;
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.words.iter_mut().for_each(|w| *w = 0);
+    }
+
+    pub fn screen(&self) -> &[u16] {
+        &self.words[SCREEN..SCREEN + SCREEN_SIZE]
+    }
+
+    // pixel at (x, y), true if black
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        if x >= SCREEN_WIDTH || y >= SCREEN_HEIGHT {
+            return false;
+        }
+        let word = self.words[SCREEN + y * SCREEN_WIDTH / 16 + x / 16];
+        word & (1 << (x % 16)) != 0
+    }
+
+    pub fn key(&self) -> u16 {
+        self.words[KBD]
+    }
+
+    // set the scan code of the currently pressed key (0 for none)
+    pub fn set_key(&mut self, code: u16) {
+        self.words[KBD] = code;
+    }
+}
@@ -1,13 +1,15 @@
 use super::parser::*;
 use super::symbols::*;
 
-use anyhow::{anyhow, Result};
+use super::error::{AsmError, ErrorKind};
+use super::expr::Expr;
 
 #[derive(Debug, Clone)]
 pub struct ACommand {
     pub symbol_name: Option<String>,
     pub addr:        usize,
     pub value:       i64,
+    pub expr:        Option<Expr>, // `@SCREEN+32`, evaluated at resolve time
     source:          Source,
 }
 
@@ -15,31 +17,69 @@ impl ACommand {
     pub fn new(addr: usize, sym: String, value: i64, source: Source) -> ACommand {
         ACommand {
             symbol_name: Some(sym),
-            addr:        addr,
-            value:       value,
-            source:      source,
+            addr,
+            value,
+            expr: None,
+            source,
         }
     }
 
     pub fn new_with_value(addr: usize, value: i64, source: Source) -> ACommand {
         ACommand {
             symbol_name: None,
-            addr:        addr,
-            value:       value,
-            source:      source,
+            addr,
+            value,
+            expr: None,
+            source,
         }
     }
 
     pub fn new_with_symbol(addr: usize, sym: String, source: Source) -> ACommand {
         ACommand {
             symbol_name: Some(sym),
-            addr:        addr,
-            value:       -1,
-            source:      source,
+            addr,
+            value: -1,
+            expr: None,
+            source,
         }
     }
 
+    pub fn new_with_expr(addr: usize, expr: Expr, source: Source) -> ACommand {
+        ACommand {
+            symbol_name: None,
+            addr,
+            value: -1,
+            expr: Some(expr),
+            source,
+        }
+    }
+
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    // the symbol, or symbols in the expression
+    pub fn symbols(&self) -> Vec<&str> {
+        match (&self.symbol_name, &self.expr) {
+            (Some(name), _) => vec![name.as_str()],
+            (None, Some(expr)) => expr.symbols(),
+            (None, None) => vec![],
+        }
+    }
+
+    // value of the expression, `None` if it overflows
+    pub fn eval(&self, symbols: &Symbols) -> Option<i64> {
+        let lookup = |name: &str| symbols.get(name.to_string()).map(|sym| sym.addr as i64);
+        self.expr.as_ref().and_then(|expr| expr.eval(&lookup))
+    }
+
     pub fn assign(self, symbols: &Symbols) -> ACommand {
+        if self.expr.is_some() {
+            // overflow is reported by validator::check_expr
+            let value = self.eval(symbols).unwrap_or(-1);
+            return ACommand { value, ..self };
+        }
+
         self.symbol_name
             .as_ref()
             .and_then(|name| {
@@ -53,21 +93,44 @@ impl ACommand {
     }
 }
 
-pub fn parse(addr: usize, source: Source) -> Result<ACommand> {
+pub fn parse(addr: usize, source: Source) -> Result<ACommand, AsmError> {
     let name = source.code.get(1..).unwrap();
 
     if let Ok(num) = name.parse::<i64>() {
-        Ok(ACommand::new_with_value(addr, num, source))
-    } else {
-        if !is_valid_symbol(name) {
-            return Err(anyhow!("{:?} : invalid symbol name: {}", source, name));
-        }
+        return Ok(ACommand::new_with_value(addr, num, source));
+    }
+    if is_valid_symbol(name) {
         let sym = String::from(name);
-        Ok(ACommand::new_with_symbol(addr, sym, source))
+        return Ok(ACommand::new_with_symbol(addr, sym, source));
     }
+    if !is_expression(name) {
+        return Err(AsmError::new(ErrorKind::InvalidSymbol, &source, 1, name));
+    }
+
+    // expressions without symbols are constants
+    match Expr::parse(name) {
+        Ok(expr) if expr.symbols().is_empty() => match expr.eval(&|_: &str| None) {
+            Some(value) => Ok(ACommand::new_with_value(addr, value, source)),
+            None => Err(
+                AsmError::new(ErrorKind::InvalidExpression, &source, 1, name)
+                    .note("arithmetic overflow"),
+            ),
+        },
+        Ok(expr) => Ok(ACommand::new_with_expr(addr, expr, source)),
+        Err((offset, token)) => Err(AsmError::new(
+            ErrorKind::InvalidExpression,
+            &source,
+            1 + offset,
+            &token,
+        )),
+    }
+}
+
+fn is_expression(name: &str) -> bool {
+    name.contains(['+', '-', '(', ')', '\''])
 }
 
-fn is_valid_symbol(name: &str) -> bool {
+pub fn is_valid_symbol(name: &str) -> bool {
     let chars: Vec<char> = name.chars().collect();
 
     if let Some((head, rest)) = chars.split_first() {
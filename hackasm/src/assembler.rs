@@ -0,0 +1,163 @@
+// assembler as a library, sources to machine words without file I/O
+//
+//   let sources = vec![(PathBuf::from("Prog.asm"), contents)];
+//   let program = assemble(&sources, &Options::default())?;
+//   program.words  machine codes
+//   program.map    symbol table and source map
+use super::c_command::Dialect;
+use super::codegen;
+use super::error::AsmError;
+use super::layout::{self, Layout, STACK_BASE};
+use super::optimizer::{self, Report};
+use super::output;
+use super::parser::{self, Node};
+use super::symbol_map::SymbolMap;
+use super::symbols::Symbols;
+use super::validator::{self, Diagnostic};
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub dialect:   Dialect,
+    pub optimize:  bool,  // peephole optimization
+    pub ram_limit: usize, // variables must be below this address
+}
+
+#[derive(Debug)]
+pub struct Program {
+    pub words:    Vec<u16>,
+    pub map:      SymbolMap,
+    pub nodes:    Vec<Node>,       // resolved nodes, for listing
+    pub warnings: Vec<Diagnostic>, // validation warnings
+    pub report:   Option<Report>,  // with `optimize`
+    pub layout:   Layout,          // ROM usage and RAM range of variables
+}
+
+// parse errors, or validation errors and warnings
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    pub errors:      Vec<AsmError>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/**
+ * 1. preprocess and parse, several sources are linked into one program
+ * 2. validate
+ * 3. peephole optimization (optional)
+ * 4. resolve symbols
+ * 5. check values of expressions, ROM size and RAM layout
+ * 6. codegen
+ */
+pub fn assemble(sources: &[(PathBuf, String)], options: &Options) -> Result<Program, Diagnostics> {
+    let mut symbols = Symbols::new();
+
+    // `.include` is relative to the source file
+    let (nodes, errors) = match sources {
+        [(file, contents)] => {
+            let dir = file.parent().unwrap_or_else(|| Path::new("."));
+            parser::parse_with_dir(contents, dir, options.dialect, &mut symbols)
+        }
+        _ => parser::parse_files(sources, options.dialect, &mut symbols),
+    };
+
+    if !errors.is_empty() {
+        return Err(Diagnostics {
+            errors,
+            diagnostics: vec![],
+        });
+    }
+
+    let diagnostics = validator::validate(&nodes);
+    if diagnostics.iter().any(|d| d.is_error()) {
+        return Err(Diagnostics {
+            errors: vec![],
+            diagnostics,
+        });
+    }
+
+    let (nodes, report) = if options.optimize {
+        let (nodes, report) = optimizer::optimize(nodes, &mut symbols);
+        (nodes, Some(report))
+    } else {
+        (nodes, None)
+    };
+
+    let nodes = symbols.resolve(nodes);
+
+    let (layout, errors) = layout::check(&nodes, &symbols, options.ram_limit);
+    let errors: Vec<Diagnostic> = validator::validate_values(&nodes, &symbols)
+        .into_iter()
+        .chain(errors)
+        .collect();
+    if !errors.is_empty() {
+        return Err(Diagnostics {
+            errors:      vec![],
+            diagnostics: diagnostics.into_iter().chain(errors).collect(),
+        });
+    }
+
+    let map = SymbolMap::build(&symbols, &nodes);
+
+    Ok(Program {
+        words: codegen::words(&nodes),
+        map,
+        nodes,
+        warnings: diagnostics,
+        report,
+        layout,
+    })
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            dialect:   Dialect::Strict,
+            optimize:  false,
+            ram_limit: STACK_BASE,
+        }
+    }
+}
+
+impl Program {
+    // contents of *.hack
+    pub fn to_hack(&self) -> String {
+        output::text(&self.words)
+    }
+}
+
+impl Diagnostics {
+    pub fn render(&self, filename: &str) -> String {
+        self.errors
+            .iter()
+            .map(|err| err.render(filename) + "\n")
+            .chain(self.diagnostics.iter().map(|d| d.render(filename)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // one object per line
+    pub fn to_json(&self) -> String {
+        self.errors
+            .iter()
+            .map(|err| err.to_json())
+            .chain(self.diagnostics.iter().map(|d| d.to_json()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<String> = self
+            .errors
+            .iter()
+            .map(|err| err.to_string())
+            .chain(self.diagnostics.iter().map(|d| d.to_string()))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for Diagnostics {}
@@ -1,6 +1,6 @@
+use super::error::{AsmError, ErrorKind};
 use super::parser::*;
 
-use anyhow::{anyhow, Result};
 use maplit::hashmap;
 
 use std::collections::HashMap;
@@ -43,55 +43,161 @@ pub enum Jump {
 // C-Command comp operand
 #[derive(Debug, Copy, Clone)]
 pub struct Comp {
-    exp:       &'static str, // expression
-    pub mcode: i8,           // machien code (7bit)
+    exp:        &'static str, // expression
+    pub mcode:  i8,           // machien code (7bit)
+    pub prefix: u8,           // top 3bit of the instruction, `101` for shifts
+}
+
+// syntax of C-Command
+//
+//   strict    the book, only canonical spellings
+//   extended  `D=A+D`, `MDA=...` and shift instructions `D<<`, `A>>`...
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Dialect {
+    #[default]
+    Strict,
+    Extended,
 }
 
 lazy_static! {
     pub static ref COMP_MAP: HashMap<&'static str, Comp> = hashmap!(
-        "0"   => Comp{ exp: "0",   mcode: 0b0101010 },
-        "1"   => Comp{ exp: "1",   mcode: 0b0111111 },
-        "-1"  => Comp{ exp: "-1",  mcode: 0b0111010 },
-        "D"   => Comp{ exp: "D",   mcode: 0b0001100 },
-        "A"   => Comp{ exp: "A",   mcode: 0b0110000 },
-        "!D"  => Comp{ exp: "!D",  mcode: 0b0001101 },
-        "!A"  => Comp{ exp: "!A",  mcode: 0b0110001 },
-        "-D"  => Comp{ exp: "-D",  mcode: 0b0001111 },
-        "-A"  => Comp{ exp: "-A",  mcode: 0b0110011 },
-        "D+1" => Comp{ exp: "D+1", mcode: 0b0011111 },
-        "A+1" => Comp{ exp: "A+1", mcode: 0b0110111 },
-        "D-1" => Comp{ exp: "D-1", mcode: 0b0001110 },
-        "A-1" => Comp{ exp: "A-1", mcode: 0b0110010 },
-        "D+A" => Comp{ exp: "D+A", mcode: 0b0000010 },
-        "D-A" => Comp{ exp: "D-A", mcode: 0b0010011 },
-        "A-D" => Comp{ exp: "A-D", mcode: 0b0000111 },
-        "D&A" => Comp{ exp: "D&A", mcode: 0b0000000 },
-        "D|A" => Comp{ exp: "D|A", mcode: 0b0010101 },
-        "M"   => Comp{ exp: "M",   mcode: 0b1110000 },
-        "!M"  => Comp{ exp: "!M",  mcode: 0b1110001 },
-        "-M"  => Comp{ exp: "-M",  mcode: 0b1110011 },
-        "M+1" => Comp{ exp: "M+1", mcode: 0b1110111 },
-        "M-1" => Comp{ exp: "M-1", mcode: 0b1110010 },
-        "D+M" => Comp{ exp: "D+M", mcode: 0b1000010 },
-        "D-M" => Comp{ exp: "D-M", mcode: 0b1010011 },
-        "M-D" => Comp{ exp: "M-D", mcode: 0b1000111 },
-        "D&M" => Comp{ exp: "D&M", mcode: 0b1000000 },
-        "D|M" => Comp{ exp: "D|M", mcode: 0b1010101 },
+        "0"   => Comp{ exp: "0",   mcode: 0b0101010, prefix: 0b111 },
+        "1"   => Comp{ exp: "1",   mcode: 0b0111111, prefix: 0b111 },
+        "-1"  => Comp{ exp: "-1",  mcode: 0b0111010, prefix: 0b111 },
+        "D"   => Comp{ exp: "D",   mcode: 0b0001100, prefix: 0b111 },
+        "A"   => Comp{ exp: "A",   mcode: 0b0110000, prefix: 0b111 },
+        "!D"  => Comp{ exp: "!D",  mcode: 0b0001101, prefix: 0b111 },
+        "!A"  => Comp{ exp: "!A",  mcode: 0b0110001, prefix: 0b111 },
+        "-D"  => Comp{ exp: "-D",  mcode: 0b0001111, prefix: 0b111 },
+        "-A"  => Comp{ exp: "-A",  mcode: 0b0110011, prefix: 0b111 },
+        "D+1" => Comp{ exp: "D+1", mcode: 0b0011111, prefix: 0b111 },
+        "A+1" => Comp{ exp: "A+1", mcode: 0b0110111, prefix: 0b111 },
+        "D-1" => Comp{ exp: "D-1", mcode: 0b0001110, prefix: 0b111 },
+        "A-1" => Comp{ exp: "A-1", mcode: 0b0110010, prefix: 0b111 },
+        "D+A" => Comp{ exp: "D+A", mcode: 0b0000010, prefix: 0b111 },
+        "D-A" => Comp{ exp: "D-A", mcode: 0b0010011, prefix: 0b111 },
+        "A-D" => Comp{ exp: "A-D", mcode: 0b0000111, prefix: 0b111 },
+        "D&A" => Comp{ exp: "D&A", mcode: 0b0000000, prefix: 0b111 },
+        "D|A" => Comp{ exp: "D|A", mcode: 0b0010101, prefix: 0b111 },
+        "M"   => Comp{ exp: "M",   mcode: 0b1110000, prefix: 0b111 },
+        "!M"  => Comp{ exp: "!M",  mcode: 0b1110001, prefix: 0b111 },
+        "-M"  => Comp{ exp: "-M",  mcode: 0b1110011, prefix: 0b111 },
+        "M+1" => Comp{ exp: "M+1", mcode: 0b1110111, prefix: 0b111 },
+        "M-1" => Comp{ exp: "M-1", mcode: 0b1110010, prefix: 0b111 },
+        "D+M" => Comp{ exp: "D+M", mcode: 0b1000010, prefix: 0b111 },
+        "D-M" => Comp{ exp: "D-M", mcode: 0b1010011, prefix: 0b111 },
+        "M-D" => Comp{ exp: "M-D", mcode: 0b1000111, prefix: 0b111 },
+        "D&M" => Comp{ exp: "D&M", mcode: 0b1000000, prefix: 0b111 },
+        "D|M" => Comp{ exp: "D|M", mcode: 0b1010101, prefix: 0b111 },
+    );
+}
+
+lazy_static! {
+    // shift instructions of the extended Hack CPU, `101` prefix instead of `111`
+    pub static ref SHIFT_MAP: HashMap<&'static str, Comp> = hashmap!(
+        "A<<" => Comp{ exp: "A<<", mcode: 0b0100000, prefix: 0b101 },
+        "D<<" => Comp{ exp: "D<<", mcode: 0b0110000, prefix: 0b101 },
+        "M<<" => Comp{ exp: "M<<", mcode: 0b1100000, prefix: 0b101 },
+        "A>>" => Comp{ exp: "A>>", mcode: 0b0000000, prefix: 0b101 },
+        "D>>" => Comp{ exp: "D>>", mcode: 0b0010000, prefix: 0b101 },
+        "M>>" => Comp{ exp: "M>>", mcode: 0b1000000, prefix: 0b101 },
     );
 }
 
-pub fn parse(addr: usize, source: Source) -> Result<CCommand> {
-    let (dest, lhs) = split_code(&source.code, "=", true);
-    let dest = dest.unwrap_or("Null");
-    let dest = parse_dest(dest)?;
+lazy_static! {
+    // inverse of COMP_MAP, used by the disassembler
+    pub static ref COMP_BY_MCODE: HashMap<i8, Comp> =
+        COMP_MAP.values().map(|comp| (comp.mcode, *comp)).collect();
 
-    let lhs = lhs.ok_or(anyhow!(
-        "{:?} :lhs operand is missing: {}",
-        source,
-        source.code
-    ))?;
+    // inverse of SHIFT_MAP
+    pub static ref SHIFT_BY_MCODE: HashMap<i8, Comp> =
+        SHIFT_MAP.values().map(|comp| (comp.mcode, *comp)).collect();
+}
+
+const DESTS: [Dest; 8] = [
+    Dest::Null,
+    Dest::M,
+    Dest::D,
+    Dest::MD,
+    Dest::A,
+    Dest::AM,
+    Dest::AD,
+    Dest::AMD,
+];
+
+const JUMPS: [Jump; 8] = [
+    Jump::Null,
+    Jump::JGT,
+    Jump::JEQ,
+    Jump::JGE,
+    Jump::JLT,
+    Jump::JNE,
+    Jump::JLE,
+    Jump::JMP,
+];
+
+impl CCommand {
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+}
+
+impl Comp {
+    pub fn exp(&self) -> &'static str {
+        self.exp
+    }
+}
+
+impl Dialect {
+    pub fn parse(name: &str) -> Option<Dialect> {
+        match name {
+            "strict" => Some(Dialect::Strict),
+            "extended" => Some(Dialect::Extended),
+            _ => None,
+        }
+    }
+}
+
+impl Dest {
+    // 3bit dest field of the machine code
+    pub fn from_bits(bits: u16) -> Dest {
+        DESTS[(bits & 0b111) as usize]
+    }
+}
 
-    let (comp, jump) = parse_comp_and_jmp(&source, lhs)?;
+impl Jump {
+    // 3bit jump field of the machine code
+    pub fn from_bits(bits: u16) -> Jump {
+        JUMPS[(bits & 0b111) as usize]
+    }
+}
+
+pub fn parse(addr: usize, source: Source) -> Result<CCommand, AsmError> {
+    parse_with_dialect(addr, source, Dialect::Strict)
+}
+
+pub fn parse_with_dialect(
+    addr: usize,
+    source: Source,
+    dialect: Dialect,
+) -> Result<CCommand, AsmError> {
+    // canonical spelling has the same length, errors point at the original code
+    let code = match dialect {
+        Dialect::Strict => source.code.clone(),
+        Dialect::Extended => canonicalize(&source.code),
+    };
+
+    let (dest, lhs) = split_code(&code, "=", true);
+    let dest = match dest {
+        Some(dest) => parse_dest(&source, dest)?,
+        None => Dest::Null,
+    };
+
+    // offset of comp operand in the code
+    let offset = code.len() - lhs.map_or(0, |lhs| lhs.len());
+    let lhs = lhs.unwrap_or("");
+
+    let (comp, jump) = parse_comp_and_jmp(&source, lhs, offset, dialect)?;
 
     let cmd = CCommand {
         dest,
@@ -103,24 +209,91 @@ pub fn parse(addr: usize, source: Source) -> Result<CCommand> {
     Ok(cmd)
 }
 
-fn parse_dest(dest: &str) -> Result<Dest> {
+fn parse_dest(source: &Source, dest: &str) -> Result<Dest, AsmError> {
     dest.parse::<Dest>()
-        .map_err(|()| anyhow!("invalid dest operand: {}", dest))
+        .ok()
+        .filter(|dest| *dest != Dest::Null)
+        .ok_or_else(|| AsmError::new(ErrorKind::InvalidDest, source, 0, dest))
 }
 
-fn parse_comp_and_jmp(source: &Source, code: &str) -> Result<(Comp, Jump)> {
-    let (comp, jump) = split_code(&code, ";", false);
-    let comp = comp.ok_or(anyhow!("{:?} : comp operand is missing: {}", source, code))?;
+fn parse_comp_and_jmp(
+    source: &Source,
+    code: &str,
+    offset: usize,
+    dialect: Dialect,
+) -> Result<(Comp, Jump), AsmError> {
+    let (comp, jump) = split_code(code, ";", false);
+    let comp = comp
+        .filter(|comp| !comp.is_empty())
+        .ok_or_else(|| AsmError::new(ErrorKind::MissingComp, source, offset, ""))?;
     let comp = COMP_MAP
         .get(comp)
-        .ok_or(anyhow!("unnown comp operand : {}", comp))?;
-    let jump = jump.unwrap_or("Null");
-    let jump = parse_jump(source, jump)?;
+        .or_else(|| match dialect {
+            Dialect::Extended => SHIFT_MAP.get(comp),
+            Dialect::Strict => None,
+        })
+        .ok_or_else(|| AsmError::new(ErrorKind::UnknownComp, source, offset, comp))?;
+    let jump = match jump {
+        Some(jump) => parse_jump(source, jump, offset + comp.exp.len() + 1)?,
+        None => Jump::Null,
+    };
 
     Ok((*comp, jump))
 }
 
-fn parse_jump(source: &Source, jump: &str) -> Result<Jump> {
+fn parse_jump(source: &Source, jump: &str, offset: usize) -> Result<Jump, AsmError> {
     jump.parse::<Jump>()
-        .map_err(|()| anyhow!("{:?} : invalid jmp operand: {}", source, jump))
+        .ok()
+        .filter(|jump| *jump != Jump::Null)
+        .ok_or_else(|| AsmError::new(ErrorKind::InvalidJump, source, offset, jump))
+}
+
+// `MDA=1+M;JMP` => `AMD=M+1;JMP`
+fn canonicalize(code: &str) -> String {
+    let (dest, lhs) = split_code(code, "=", true);
+    let lhs = lhs.unwrap_or("");
+    let (comp, jump) = split_code(lhs, ";", false);
+
+    let mut canonical = String::new();
+    if let Some(dest) = dest {
+        canonical.push_str(&canonical_dest(dest));
+        canonical.push('=');
+    }
+    canonical.push_str(&canonical_comp(comp.unwrap_or("")));
+    if let Some(jump) = jump {
+        canonical.push(';');
+        canonical.push_str(jump);
+    }
+    canonical
+}
+
+// letters of dest in the order of `AMD`, unless repeated or unknown
+fn canonical_dest(dest: &str) -> String {
+    let valid = dest.chars().all(|c| "AMD".contains(c))
+        && dest.chars().all(|c| dest.matches(c).count() == 1);
+    if !valid {
+        return dest.to_string();
+    }
+
+    "AMD".chars().filter(|c| dest.contains(*c)).collect()
+}
+
+// `A+D` => `D+A`, operands of `+`, `&` and `|` are swapped when it makes a known comp
+fn canonical_comp(comp: &str) -> String {
+    if COMP_MAP.contains_key(comp) {
+        return comp.to_string();
+    }
+
+    let chars: Vec<char> = comp.chars().collect();
+    match chars[..] {
+        [x, op, y] if "+&|".contains(op) => {
+            let swapped: String = [y, op, x].iter().collect();
+            if COMP_MAP.contains_key(swapped.as_str()) {
+                swapped
+            } else {
+                comp.to_string()
+            }
+        }
+        _ => comp.to_string(),
+    }
 }
@@ -5,7 +5,7 @@ use super::parser::Node;
 pub fn generate(nodes: Vec<Node>) -> String {
     nodes
         .iter()
-        .flat_map(|node| gen(node))
+        .flat_map(gen)
         .collect::<Vec<String>>()
         .join("\n")
         + "\n"
@@ -19,6 +19,24 @@ fn gen(node: &Node) -> Option<String> {
     }
 }
 
+// 16bit machine code of the node, `None` for labels
+pub fn encode(node: &Node) -> Option<u16> {
+    match node {
+        Node::A(a) => Some(a.value as u16 & 0x7fff),
+        Node::C(c) => Some(
+            (c.comp.prefix as u16) << 13
+                | (c.comp.mcode as u16) << 6
+                | (c.dest as u16) << 3
+                | c.jump as u16,
+        ),
+        _ => None,
+    }
+}
+
+pub fn words(nodes: &[Node]) -> Vec<u16> {
+    nodes.iter().filter_map(encode).collect()
+}
+
 fn gen_a(a: &ACommand) -> String {
     // format!("0{:015b}  : {:?}", a.value, a)
     format!("0{:015b}", a.value)
@@ -26,7 +44,7 @@ fn gen_a(a: &ACommand) -> String {
 
 fn gen_c(c: &CCommand) -> String {
     format!(
-        "111{:07b}{:03b}{:03b}",
-        c.comp.mcode, c.dest as i8, c.jump as i8
+        "{:03b}{:07b}{:03b}{:03b}",
+        c.comp.prefix, c.comp.mcode, c.dest as i8, c.jump as i8
     )
 }
@@ -0,0 +1,144 @@
+// disassembler: machine codes (*.hack) to assembly
+use super::c_command::{Dest, Jump, COMP_BY_MCODE, SHIFT_BY_MCODE};
+use super::parser::Source;
+use super::symbol_map::SymbolMap;
+
+use anyhow::{anyhow, Result};
+
+use std::collections::HashMap;
+
+pub type DisassembleResult = (String, Vec<anyhow::Error>);
+
+pub fn disassemble(contents: &str, symbols: &SymbolMap) -> DisassembleResult {
+    let mut errors = Vec::new();
+
+    let sources: Vec<Source> = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, code)| !code.trim().is_empty())
+        .map(|(line, code)| Source {
+            file: None,
+            line: line + 1,
+            code: code.trim().to_string(),
+        })
+        .collect();
+
+    let words: Vec<Option<u16>> = sources
+        .iter()
+        .map(|source| parse_word(&source.code).ok())
+        .collect();
+
+    // ROM address => labels
+    let mut labels: HashMap<usize, Vec<&str>> = HashMap::new();
+    symbols
+        .labels
+        .iter()
+        .for_each(|(name, addr)| labels.entry(*addr).or_default().push(name));
+
+    // RAM address => variable
+    let variables: HashMap<usize, &str> = symbols
+        .variables
+        .iter()
+        .map(|(name, addr)| (*addr, name.as_str()))
+        .collect();
+
+    let mut lines = Vec::new();
+    for (addr, (source, word)) in sources.iter().zip(words.iter()).enumerate() {
+        if let Some(names) = labels.get(&addr) {
+            names
+                .iter()
+                .for_each(|name| lines.push(format!("({})", name)));
+        }
+
+        let word = match word {
+            Some(word) => *word,
+            None => {
+                if let Err(err) = parse_word(&source.code) {
+                    errors.push(anyhow!("{:?} : {}", source, err));
+                }
+                lines.push(format!("    // invalid : {}", source.code));
+                continue;
+            }
+        };
+
+        // `@LABEL` only when the next instruction jumps
+        let next_jumps = words
+            .get(addr + 1)
+            .copied()
+            .flatten()
+            .is_some_and(|next| is_c(next) && Jump::from_bits(next) != Jump::Null);
+
+        let symbol = if is_c(word) {
+            None
+        } else if next_jumps {
+            labels.get(&(word as usize)).map(|names| names[0])
+        } else {
+            variables.get(&(word as usize)).copied()
+        };
+
+        match (decode(word), symbol) {
+            (Ok(_), Some(symbol)) => lines.push(format!("    @{}", symbol)),
+            (Ok(code), None) => lines.push(format!("    {}", code)),
+            (Err(err), _) => {
+                errors.push(anyhow!("{:?} : {}", source, err));
+                lines.push(format!("    // invalid : {}", source.code));
+            }
+        }
+    }
+
+    // labels at the end of the program
+    if let Some(names) = labels.get(&sources.len()) {
+        names
+            .iter()
+            .for_each(|name| lines.push(format!("({})", name)));
+    }
+
+    (lines.join("\n") + "\n", errors)
+}
+
+// decode one machine code to `@value` or `dest=comp;jump`
+pub fn decode(word: u16) -> Result<String> {
+    if !is_c(word) {
+        return Ok(format!("@{}", word));
+    }
+
+    // `101` is a shift instruction of the extended dialect
+    let mcode = (word >> 6 & 0b1111111) as i8;
+    let comps = match word >> 13 {
+        0b111 => &*COMP_BY_MCODE,
+        0b101 => &*SHIFT_BY_MCODE,
+        _ => {
+            return Err(anyhow!(
+                "unused bits of C-instruction must be 1 : {:016b}",
+                word
+            ))
+        }
+    };
+    let comp = comps
+        .get(&mcode)
+        .ok_or_else(|| anyhow!("unknown comp bits : {:07b}", mcode))?;
+    let dest = Dest::from_bits(word >> 3);
+    let jump = Jump::from_bits(word);
+
+    let mut code = String::new();
+    if dest != Dest::Null {
+        code.push_str(&format!("{:?}=", dest));
+    }
+    code.push_str(comp.exp());
+    if jump != Jump::Null {
+        code.push_str(&format!(";{:?}", jump));
+    }
+
+    Ok(code)
+}
+
+fn is_c(word: u16) -> bool {
+    word & 0x8000 != 0
+}
+
+fn parse_word(code: &str) -> Result<u16> {
+    if code.len() != 16 || !code.chars().all(|c| c == '0' || c == '1') {
+        return Err(anyhow!("expected 16 binary digits"));
+    }
+    u16::from_str_radix(code, 2).map_err(|err| anyhow!("{}", err))
+}
@@ -0,0 +1,146 @@
+// parse errors with location
+//
+//   error: unknown comp operand `D+2`
+//     --> Prog.asm:12:7
+//      |
+//   12 |     D=D+2;JGT
+//      |       ^^^
+use super::parser::{code_chars, strip_whitespaces, Source};
+
+use serde::Serialize;
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum ErrorKind {
+    InvalidDest,       // `X=D`
+    MissingComp,       // `D=` or `;JMP`
+    UnknownComp,       // `D=D+2`
+    InvalidJump,       // `0;JMPP`
+    InvalidSymbol,     // `@1abc`, `(a b)`
+    MissingParen,      // `(LOOP`
+    InvalidExpression, // `@SCREEN+`, `@(KBD-1`
+    // preprocessor
+    UnknownDirective,    // `.foo`
+    InvalidDirective,    // `.define`, `.macro 1`
+    UnbalancedDirective, // `.endif` without `.if`, `.macro` without `.endm`
+    InvalidInclude,      // unreadable or recursive `.include`
+    MacroArguments,      // wrong number of macro arguments
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AsmError {
+    pub kind:    ErrorKind,
+    pub message: String,
+    pub file:    Option<String>, // included file, `None` for the main file
+    pub line:    usize,          // line number in source file
+    pub column:  usize,          // 1-based column of the offending operand
+    pub len:     usize,          // width of the offending operand
+    pub text:    String,         // the source line
+}
+
+impl ErrorKind {
+    fn describe(&self) -> &'static str {
+        match self {
+            ErrorKind::InvalidDest => "invalid dest operand",
+            ErrorKind::MissingComp => "comp operand is missing",
+            ErrorKind::UnknownComp => "unknown comp operand",
+            ErrorKind::InvalidJump => "invalid jump operand",
+            ErrorKind::InvalidSymbol => "invalid symbol name",
+            ErrorKind::MissingParen => "expected `)`",
+            ErrorKind::InvalidExpression => "invalid expression",
+            ErrorKind::UnknownDirective => "unknown directive",
+            ErrorKind::InvalidDirective => "invalid directive",
+            ErrorKind::UnbalancedDirective => "unbalanced directive",
+            ErrorKind::InvalidInclude => "cannot include",
+            ErrorKind::MacroArguments => "wrong number of macro arguments",
+        }
+    }
+}
+
+impl AsmError {
+    // `offset` and `operand` are relative to `source.code`
+    pub fn new(kind: ErrorKind, source: &Source, offset: usize, operand: &str) -> AsmError {
+        let message = if operand.is_empty() {
+            kind.describe().to_string()
+        } else {
+            format!("{} `{}`", kind.describe(), operand)
+        };
+
+        AsmError {
+            kind,
+            message,
+            file: source.file.clone(),
+            line: source.line,
+            column: offset + 1,
+            len: operand.chars().count().max(1),
+            text: source.code.clone(),
+        }
+    }
+
+    // append detail to the message
+    pub fn note(mut self, note: &str) -> AsmError {
+        self.message = format!("{} : {}", self.message, note);
+        self
+    }
+
+    // map the column in the code without whitespaces and comments to the original line,
+    // lines expanded from macros have no original text
+    pub fn locate(&mut self, text: &str) {
+        let code = text.split("//").next().unwrap_or("");
+        if strip_whitespaces(code) != strip_whitespaces(&self.text) {
+            return;
+        }
+
+        let columns: Vec<usize> = code_chars(text).into_iter().map(|(i, _)| i + 1).collect();
+
+        let end = self.column - 1 + self.len - 1;
+        let start = columns.get(self.column - 1).copied();
+        let end = columns.get(end).copied();
+
+        match (start, end) {
+            (Some(start), Some(end)) => {
+                self.column = start;
+                self.len = end - start + 1;
+            }
+            // past the end of code, e.g. missing `)`
+            (Some(start), None) => self.column = start,
+            _ => self.column = text.trim_end().chars().count() + 1,
+        }
+        self.text = text.to_string();
+    }
+
+    // rustc style message with the underlined operand
+    pub fn render(&self, filename: &str) -> String {
+        let filename = self.file.as_deref().unwrap_or(filename);
+        let lineno = self.line.to_string();
+        let pad = " ".repeat(lineno.len());
+
+        format!(
+            "error: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+            self.message,
+            pad,
+            filename,
+            self.line,
+            self.column,
+            pad,
+            lineno,
+            self.text.replace('\t', " "),
+            pad,
+            " ".repeat(self.column - 1),
+            "^".repeat(self.len),
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{} : {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
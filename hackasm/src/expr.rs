@@ -0,0 +1,158 @@
+// constant expressions of A-Command operands
+//
+//   @SCREEN+32   @(KBD-1)   @LABEL+2   @'A'
+//
+//   expr := term (('+' | '-') term)*
+//   term := number | symbol | 'c' | '(' expr ')'
+use super::a_command::is_valid_symbol;
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Num(i64),
+    Sym(String),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+}
+
+// offset of the invalid token in the expression and the token
+pub type ExprError = (usize, String);
+
+struct Parser {
+    chars: Vec<char>,
+    pos:   usize,
+}
+
+impl Expr {
+    pub fn parse(code: &str) -> Result<Expr, ExprError> {
+        let mut parser = Parser {
+            chars: code.chars().collect(),
+            pos:   0,
+        };
+        let expr = parser.expr()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(_) => Err(parser.error(parser.pos, parser.chars.len())),
+        }
+    }
+
+    // symbols in order of appearance
+    pub fn symbols(&self) -> Vec<&str> {
+        match self {
+            Expr::Num(_) => vec![],
+            Expr::Sym(name) => vec![name.as_str()],
+            Expr::Add(lhs, rhs) | Expr::Sub(lhs, rhs) => {
+                let mut symbols = lhs.symbols();
+                symbols.extend(rhs.symbols());
+                symbols
+            }
+        }
+    }
+
+    pub fn rename<F: Fn(&str) -> Option<String>>(&mut self, f: &F) {
+        match self {
+            Expr::Num(_) => {}
+            Expr::Sym(name) => {
+                if let Some(renamed) = f(name) {
+                    *name = renamed;
+                }
+            }
+            Expr::Add(lhs, rhs) | Expr::Sub(lhs, rhs) => {
+                lhs.rename(f);
+                rhs.rename(f);
+            }
+        }
+    }
+
+    // `None` if a symbol is undefined or the value overflows
+    pub fn eval<F: Fn(&str) -> Option<i64>>(&self, lookup: &F) -> Option<i64> {
+        match self {
+            Expr::Num(n) => Some(*n),
+            Expr::Sym(name) => lookup(name),
+            Expr::Add(lhs, rhs) => lhs.eval(lookup)?.checked_add(rhs.eval(lookup)?),
+            Expr::Sub(lhs, rhs) => lhs.eval(lookup)?.checked_sub(rhs.eval(lookup)?),
+        }
+    }
+}
+
+impl Parser {
+    fn expr(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.term()?;
+        while let Some(op) = self.peek().filter(|c| *c == '+' || *c == '-') {
+            self.pos += 1;
+            let rhs = self.term()?;
+            lhs = match op {
+                '+' => Expr::Add(Box::new(lhs), Box::new(rhs)),
+                _ => Expr::Sub(Box::new(lhs), Box::new(rhs)),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, ExprError> {
+        let start = self.pos;
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                match self.peek() {
+                    Some(')') => {
+                        self.pos += 1;
+                        Ok(expr)
+                    }
+                    _ => Err(self.error(start, self.chars.len())),
+                }
+            }
+            Some('\'') => match (self.chars.get(start + 1), self.chars.get(start + 2)) {
+                (Some(c), Some('\'')) if *c != '\'' => {
+                    self.pos += 3;
+                    Ok(Expr::Num(*c as i64))
+                }
+                _ => Err(self.error(start, (start + 3).min(self.chars.len()))),
+            },
+            Some(_) => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !matches!(c, '+' | '-' | '(' | ')' | '\''))
+                {
+                    self.pos += 1;
+                }
+                let token: String = self.chars[start..self.pos].iter().collect();
+
+                if let Ok(n) = token.parse::<i64>() {
+                    Ok(Expr::Num(n))
+                } else if is_valid_symbol(&token) {
+                    Ok(Expr::Sym(token))
+                } else {
+                    Err(self.error(start, self.pos.max(start + 1)))
+                }
+            }
+            None => Err(self.error(start, start)),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, start: usize, end: usize) -> ExprError {
+        let len = self.chars.len();
+        let token: String = self.chars[start.min(len)..end.min(len)].iter().collect();
+        (start, token)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Num(n) => write!(f, "{}", n),
+            Expr::Sym(name) => write!(f, "{}", name),
+            Expr::Add(lhs, rhs) => write!(f, "{}+{}", lhs, rhs),
+            Expr::Sub(lhs, rhs) => match **rhs {
+                Expr::Add(_, _) | Expr::Sub(_, _) => write!(f, "{}-({})", lhs, rhs),
+                _ => write!(f, "{}-{}", lhs, rhs),
+            },
+        }
+    }
+}
@@ -1,8 +1,8 @@
+use super::a_command::is_valid_symbol;
+use super::error::{AsmError, ErrorKind};
 use super::parser::*;
 use super::symbols::*;
 
-use anyhow::{anyhow, Result};
-
 #[derive(Debug)]
 pub struct LCommand {
     pub symbol: Symbol,
@@ -10,16 +10,26 @@ pub struct LCommand {
     source:     Source,
 }
 
-pub fn parse(addr: usize, source: Source) -> Result<LCommand> {
-    if !source.code.ends_with(")") {
-        return Err(anyhow!("{:?} : expected `)`", source));
+impl LCommand {
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+}
+
+pub fn parse(addr: usize, source: Source) -> Result<LCommand, AsmError> {
+    if !source.code.ends_with(')') {
+        let offset = source.code.len();
+        return Err(AsmError::new(ErrorKind::MissingParen, &source, offset, ""));
     }
     let name = source.code.get(1..(source.code.len() - 1)).unwrap();
-    let symbol = Symbol::new(String::from(name), addr);
+    if !is_valid_symbol(name) {
+        return Err(AsmError::new(ErrorKind::InvalidSymbol, &source, 1, name));
+    }
+    let symbol = Symbol::new(String::from(name), addr, SymbolKind::Label);
 
     Ok(LCommand {
         symbol,
-        addr: addr,
+        addr,
         source,
     })
 }
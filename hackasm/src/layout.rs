@@ -0,0 +1,113 @@
+// ROM usage and RAM layout after symbols are resolved
+//
+//   ROM      0..32767   instructions
+//   RAM      0..15      R0-R15 (SP, LCL, ARG, THIS, THAT, temp)
+//            16..       variables, up to the stack at 256 by default
+use super::parser::{Node, Source};
+use super::symbols::{SymbolKind, Symbols};
+use super::validator::Diagnostic;
+
+use serde::Serialize;
+
+use std::fmt;
+
+// words of ROM
+pub const ROM_SIZE: usize = 0x8000;
+
+// first RAM address of variables
+pub const VARIABLE_BASE: usize = 16;
+
+// stack of the vmtranslator prelude, `@256 D=A @SP M=D`
+pub const STACK_BASE: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Layout {
+    pub rom:       usize,                  // words of the program
+    pub variables: Option<(usize, usize)>, // first and last RAM address of variables
+    pub ram_limit: usize,                  // variables must be below this address
+}
+
+pub fn check(nodes: &[Node], symbols: &Symbols, ram_limit: usize) -> (Layout, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+
+    let rom = nodes
+        .iter()
+        .filter(|node| !matches!(node, Node::L(_)))
+        .count();
+    if rom > ROM_SIZE {
+        // the first instruction out of ROM
+        if let Some(source) = nodes.iter().find_map(|node| match node {
+            Node::A(a) if a.addr == ROM_SIZE => Some(a.source()),
+            Node::C(c) if c.addr == ROM_SIZE => Some(c.source()),
+            _ => None,
+        }) {
+            diagnostics.push(Diagnostic::error(source, rom_overflow(rom)));
+        }
+    }
+
+    let variables = symbols.symbols(SymbolKind::Variable);
+    // the first variable over the limit, the rest follow it
+    if let Some(first) = variables.iter().find(|sym| sym.addr >= ram_limit) {
+        if let Some(source) = reference(nodes, &first.name) {
+            diagnostics.push(Diagnostic::error(
+                source,
+                ram_overflow(&first.name, first.addr, ram_limit),
+            ));
+        }
+    }
+
+    let layout = Layout {
+        rom,
+        variables: variables
+            .first()
+            .zip(variables.last())
+            .map(|(first, last)| (first.addr, last.addr)),
+        ram_limit,
+    };
+    (layout, diagnostics)
+}
+
+pub fn rom_overflow(words: usize) -> String {
+    format!(
+        "program needs {} words, exceeds ROM of {} words",
+        words, ROM_SIZE
+    )
+}
+
+pub fn ram_overflow(name: &str, addr: usize, ram_limit: usize) -> String {
+    format!(
+        "variable {} is allocated at RAM[{}], over the limit {}",
+        name, addr, ram_limit
+    )
+}
+
+// the first A-Command loading the symbol
+fn reference<'a>(nodes: &'a [Node], name: &str) -> Option<&'a Source> {
+    nodes.iter().find_map(|node| match node {
+        Node::A(a) if a.symbols().contains(&name) => Some(a.source()),
+        _ => None,
+    })
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "ROM : {} / {} words ({:.1}%)",
+            self.rom,
+            ROM_SIZE,
+            self.rom as f64 * 100.0 / ROM_SIZE as f64
+        )?;
+        match self.variables {
+            Some((first, last)) => write!(
+                f,
+                "RAM : variables {}..{} ({} words), limit {}",
+                first,
+                last,
+                last - first + 1,
+                self.ram_limit
+            ),
+            None => write!(f, "RAM : no variables, limit {}", self.ram_limit),
+        }
+    }
+}
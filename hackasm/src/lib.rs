@@ -2,8 +2,23 @@
 extern crate lazy_static;
 
 pub mod a_command;
+pub mod assembler;
 pub mod c_command;
 pub mod codegen;
+pub mod disassembler;
+pub mod error;
+pub mod expr;
 pub mod l_command;
+pub mod layout;
+pub mod listing;
+pub mod optimizer;
+pub mod output;
 pub mod parser;
+pub mod preprocessor;
+pub mod repl;
+pub mod stream;
+pub mod symbol_map;
 pub mod symbols;
+pub mod validator;
+
+pub use assembler::{assemble, Diagnostics, Options, Program};
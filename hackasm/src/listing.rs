@@ -0,0 +1,172 @@
+// assembly listing (*.lst)
+//
+//   ADDR  WORD  BINARY              LINE  SOURCE
+//                                        1  // Rect.asm
+//   0000  0000  0000000000000000       9  @0
+//   000A                              19  (LOOP)
+//
+// followed by the cross-reference table of symbols
+use super::codegen;
+use super::parser::{Node, Source};
+use super::symbols;
+
+use std::collections::{BTreeMap, HashMap};
+
+// original lines of each file, `None` for the main file
+pub type SourceLines = HashMap<Option<String>, Vec<String>>;
+
+const HEADER: &str = "ADDR  WORD  BINARY              LINE  SOURCE";
+
+// symbol in cross-reference table
+struct Entry<'a> {
+    kind:       &'static str,
+    value:      Option<i64>,
+    definition: Option<&'a Source>,
+    references: Vec<&'a Source>,
+}
+
+pub fn generate(nodes: &[Node], lines: &SourceLines) -> String {
+    let mut listing = vec![HEADER.to_string()];
+
+    // next line to print of each file
+    let mut cursors: HashMap<Option<String>, usize> = HashMap::new();
+
+    nodes.iter().for_each(|node| {
+        let (addr, source) = match node {
+            Node::A(a) => (a.addr, a.source()),
+            Node::C(c) => (c.addr, c.source()),
+            Node::L(l) => (l.addr, l.source()),
+        };
+
+        // lines without code before this node, e.g. comments and directives
+        let cursor = cursors.entry(source.file.clone()).or_insert(1);
+        if let Some(lines) = lines.get(&source.file) {
+            while *cursor < source.line {
+                listing.push(format!("{:30}{:>6}  {}", "", *cursor, lines[*cursor - 1]));
+                *cursor += 1;
+            }
+        }
+
+        // expanded macros share the line of the call
+        let text = match lines
+            .get(&source.file)
+            .and_then(|lines| lines.get(source.line - 1))
+        {
+            Some(text) if *cursor == source.line => text.clone(),
+            _ => format!("    {}", source.code),
+        };
+        *cursor = source.line + 1;
+
+        listing.push(match codegen::encode(node) {
+            Some(word) => format!(
+                "{:04X}  {:04X}  {:016b}  {:>6}  {}",
+                addr, word, word, source.line, text
+            ),
+            None => format!("{:04X}{:26}{:>6}  {}", addr, "", source.line, text),
+        });
+    });
+
+    // rest of lines
+    let mut files: Vec<&Option<String>> = lines.keys().collect();
+    files.sort();
+    files.into_iter().for_each(|file| {
+        let cursor = cursors.get(file).copied().unwrap_or(1);
+        lines[file]
+            .iter()
+            .enumerate()
+            .skip(cursor - 1)
+            .for_each(|(n, line)| listing.push(format!("{:30}{:>6}  {}", "", n + 1, line)));
+    });
+
+    listing.push(String::new());
+    listing.extend(cross_reference(nodes));
+
+    listing.join("\n") + "\n"
+}
+
+// SYMBOL  KIND  VALUE  DEFINED  REFERENCES
+fn cross_reference(nodes: &[Node]) -> Vec<String> {
+    let mut entries: BTreeMap<&str, Entry> = BTreeMap::new();
+
+    nodes.iter().for_each(|node| match node {
+        Node::L(l) => {
+            let entry = entries
+                .entry(&l.symbol.name)
+                .or_insert_with(|| Entry::new("label"));
+            entry.kind = "label";
+            entry.value = Some(l.symbol.addr as i64);
+            entry.definition = entry.definition.or(Some(l.source()));
+        }
+        Node::A(a) => a.symbols().into_iter().for_each(|name| {
+            let kind = if symbols::is_predefined(name) {
+                "predefined"
+            } else {
+                "variable"
+            };
+            let entry = entries.entry(name).or_insert_with(|| Entry::new(kind));
+            // the value of an expression is not the address of the symbol
+            if a.expr.is_none() {
+                entry.value = entry.value.or(Some(a.value)).filter(|value| *value >= 0);
+            }
+            entry.references.push(a.source());
+        }),
+        Node::C(_) => {}
+    });
+
+    let width = entries
+        .keys()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+    let mut table = vec![format!(
+        "{:width$}  {:10}  {:>5}  {:12}  REFERENCES",
+        "SYMBOL",
+        "KIND",
+        "VALUE",
+        "DEFINED",
+        width = width
+    )];
+
+    table.extend(entries.iter().map(|(name, entry)| {
+        let value = entry
+            .value
+            .map_or_else(|| String::from("-"), |value| value.to_string());
+        let definition = entry.definition.map_or_else(|| String::from("-"), location);
+        let references: Vec<String> = entry
+            .references
+            .iter()
+            .map(|source| location(source))
+            .collect();
+        format!(
+            "{:width$}  {:10}  {:>5}  {:12}  {}",
+            name,
+            entry.kind,
+            value,
+            definition,
+            references.join(" "),
+            width = width
+        )
+    }));
+
+    table
+}
+
+impl<'a> Entry<'a> {
+    fn new(kind: &'static str) -> Entry<'a> {
+        Entry {
+            kind,
+            value: None,
+            definition: None,
+            references: vec![],
+        }
+    }
+}
+
+// `12` or `lib.asm:12`
+fn location(source: &Source) -> String {
+    match &source.file {
+        Some(file) => format!("{}:{}", file, source.line),
+        None => source.line.to_string(),
+    }
+}
@@ -1,62 +1,417 @@
 extern crate hackasm;
 
-use hackasm::codegen;
-use hackasm::parser;
-use hackasm::symbols::Symbols;
+use hackasm::assembler::{self, Diagnostics};
+use hackasm::c_command::Dialect;
+use hackasm::disassembler;
+use hackasm::layout::STACK_BASE;
+use hackasm::listing::{self, SourceLines};
+use hackasm::output::{self, Format};
+use hackasm::parser::Node;
+use hackasm::repl::Repl;
+use hackasm::stream;
+use hackasm::symbol_map::SymbolMap;
+
+use getopts::Options;
 
 use std::env;
-use std::fs::File;
-use std::io::prelude::*;
+use std::fs::{self, File};
 use std::io::Write;
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
 use std::process;
+
+struct Config {
+    filename:    String,      // first input
+    inputs:      Vec<String>, // *.asm files or a directory
+    output:      Option<String>,
+    format:      Format,
+    dialect:     Dialect,
+    optimize:    bool,
+    stream:      bool,
+    layout:      bool,
+    ram_limit:   usize,
+    disassemble: bool,
+    symbols:     Option<String>,
+    map:         Option<String>,
+    json:        bool,
+}
+
 /**
- * 1. parse
- * 2. resolve symbols
- * 3. codegen
+ * 1. collect and read *.asm files
+ * 2. assemble
+ * 3. codegen in the output format
+ * 4. write symbol and source map (optional)
  */
-
 fn main() {
-    // get filename from args
-    let filename = get_filename();
+    let config = parse_args();
 
-    // check filename ext
-    valiate_filename(&filename);
+    if config.disassemble {
+        disassemble(&config);
+        return;
+    }
 
-    // read file
-    let contents = read_source(&filename);
+    if config.filename == "repl" {
+        let stdin = io::stdin();
+        Repl::new(config.dialect)
+            .run(stdin.lock(), &mut io::stdout())
+            .unwrap_or_else(|err| {
+                println!("repl error: {}", err);
+                process::exit(1);
+            });
+        return;
+    }
 
-    let mut symbols = Symbols::new();
+    // *.asm files to link
+    let files = collect_files(&config.inputs);
+    files
+        .iter()
+        .for_each(|file| valiate_filename(&file.display().to_string()));
+    let filename = files[0].display().to_string();
 
-    // parse
-    let (nodes, errors) = parser::parse(&contents, &mut symbols);
+    let output = config
+        .output
+        .clone()
+        .unwrap_or_else(|| output_filename(&config.inputs, config.format));
 
-    if !errors.is_empty() {
-        println!("parse error: ");
-        errors.into_iter().for_each(|err| println!("  {:?}", err));
-        process::exit(1);
+    if config.stream {
+        stream(&config, &files, &filename, &output);
+        return;
     }
 
-    // resolve symbols
-    let nodes = symbols.resolve(nodes);
+    // read files
+    let files: Vec<(PathBuf, String)> = files
+        .into_iter()
+        .map(|file| {
+            let contents = read_source(&file.display().to_string());
+            (file, contents)
+        })
+        .collect();
 
-    // println!("Nodes: ");
-    // nodes.iter().for_each(|node| println!("  {:?}", node));
+    let options = options(&config);
+    let program = assembler::assemble(&files, &options).unwrap_or_else(|diagnostics| {
+        match config.json {
+            true => println!("{}", diagnostics.to_json()),
+            false => println!("{}", diagnostics.render(&filename)),
+        }
+        process::exit(1);
+    });
+
+    program.warnings.iter().for_each(|d| match config.json {
+        true => println!("{}", d.to_json()),
+        false => println!("{}", d.render(&filename)),
+    });
+
+    if let Some(report) = &program.report {
+        println!("{}", report);
+    }
+
+    if config.layout {
+        println!("{}", program.layout);
+    }
 
-    // generate code
-    let mcodes = codegen::generate(nodes);
+    // generate code, listing shows the original lines
+    let mcodes = match config.format {
+        Format::Listing => {
+            listing::generate(&program.nodes, &source_lines(&files, &program.nodes)).into_bytes()
+        }
+        _ => output::emit(&program.nodes, config.format),
+    };
 
     // write to file
-    write_mcodes(&filename, &mcodes);
+    write_mcodes(&output, &mcodes);
+
+    if let Some(format) = &config.map {
+        write_map(&output, format, &program.map);
+    }
 }
 
-fn get_filename() -> String {
-    // get filename from args
+fn options(config: &Config) -> assembler::Options {
+    assembler::Options {
+        dialect:   config.dialect,
+        optimize:  config.optimize,
+        ram_limit: config.ram_limit,
+    }
+}
+
+fn parse_args() -> Config {
     let args: Vec<String> = env::args().collect();
-    let name = args.get(1).unwrap_or_else(|| {
+    let mut opts = Options::new();
+
+    opts.optflag("d", "disassemble", "disassemble FILE.hack to stdout");
+    opts.optflag(
+        "O",
+        "optimize",
+        "remove redundant instructions and print a report",
+    );
+    opts.optflag(
+        "",
+        "stream",
+        "two-pass streaming for large inputs, without directives (text or bin format)",
+    );
+    opts.optflag("", "layout", "print ROM usage and RAM range of variables");
+    opts.optopt(
+        "",
+        "ram-limit",
+        "variables must be below this RAM address (default 256, the stack)",
+        "ADDR",
+    );
+    opts.optflag("j", "json", "print errors as JSON, one object per line");
+    opts.optopt(
+        "s",
+        "symbols",
+        "symbol file to restore label and variable names",
+        "FILE.sym",
+    );
+    opts.optopt(
+        "o",
+        "output",
+        "output file (default FILE.hack or DIR/DIR.hack)",
+        "FILE.hack",
+    );
+    opts.optopt(
+        "f",
+        "format",
+        "output format (default text)",
+        "text|bin|hex|logisim|lst",
+    );
+    opts.optopt(
+        "",
+        "dialect",
+        "C-Command syntax, extended accepts `D=A+D`, `MDA=...` and shifts (default strict)",
+        "strict|extended",
+    );
+    opts.optopt(
+        "m",
+        "map",
+        "write symbol and source map as Prog.sym or Prog.sym.json",
+        "sym|json",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            process::exit(1);
+        }
+    };
+
+    let filename = matches.free.first().cloned().unwrap_or_else(|| {
         println!("not enough arguments");
+        println!(
+            "{}",
+            opts.usage("usage: hackasm [options] FILE.asm...|DIR|FILE.hack|repl")
+        );
+        process::exit(1);
+    });
+
+    let map = matches.opt_str("map");
+    if let Some(format) = map.as_deref().filter(|f| *f != "sym" && *f != "json") {
+        println!(
+            "invalid map format, expected to 'sym' or 'json': {:?}",
+            format
+        );
+        process::exit(1);
+    }
+
+    let format = Format::parse(
+        &matches
+            .opt_str("format")
+            .unwrap_or_else(|| String::from("text")),
+    )
+    .unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(1);
+    });
+
+    let dialect = matches
+        .opt_str("dialect")
+        .unwrap_or_else(|| String::from("strict"));
+    let dialect = Dialect::parse(&dialect).unwrap_or_else(|| {
+        println!(
+            "invalid dialect, expected to 'strict' or 'extended': {:?}",
+            dialect
+        );
+        process::exit(1);
+    });
+
+    let ram_limit = match matches.opt_str("ram-limit") {
+        Some(limit) => limit.parse::<usize>().unwrap_or_else(|_| {
+            println!("invalid RAM limit, expected to a number: {:?}", limit);
+            process::exit(1);
+        }),
+        None => STACK_BASE,
+    };
+
+    Config {
+        filename,
+        inputs: matches.free.clone(),
+        output: matches.opt_str("output"),
+        format,
+        dialect,
+        optimize: matches.opt_present("optimize"),
+        stream: matches.opt_present("stream"),
+        layout: matches.opt_present("layout"),
+        ram_limit,
+        disassemble: matches.opt_present("disassemble"),
+        symbols: matches.opt_str("symbols"),
+        map,
+        json: matches.opt_present("json"),
+    }
+}
+
+/**
+ * 1. pass 1 records labels
+ * 2. pass 2 writes machine codes to the output file
+ */
+fn stream(config: &Config, files: &[PathBuf], filename: &str, output: &str) {
+    if config.optimize || config.map.is_some() {
+        println!("--stream cannot be used with --optimize or --map");
+        process::exit(1);
+    }
+
+    let out = File::create(output).unwrap_or_else(|err| {
+        println!("cannot open file: {}", err);
+        process::exit(1);
+    });
+
+    match stream::assemble(files, &options(config), config.format, out) {
+        Ok((_, warnings)) => {
+            warnings.iter().for_each(|d| match config.json {
+                true => println!("{}", d.to_json()),
+                false => println!("{}", d.render(filename)),
+            });
+            println!("write mcodes to {}", output)
+        }
+        Err(err) => {
+            match err.downcast_ref::<Diagnostics>() {
+                Some(diagnostics) if config.json => println!("{}", diagnostics.to_json()),
+                Some(diagnostics) => println!("{}", diagnostics.render(filename)),
+                None => println!("{}", err),
+            }
+            let _ = fs::remove_file(output);
+            process::exit(1);
+        }
+    }
+}
+
+/**
+ * 1. read symbol file
+ * 2. decode machine codes
+ * 3. print assembly
+ */
+fn disassemble(config: &Config) {
+    if !config.filename.ends_with(".hack") {
+        println!(
+            "invalid filename, exptected to '*.hack': {:?}",
+            config.filename
+        );
         process::exit(1);
+    }
+
+    let symbols = match &config.symbols {
+        Some(filename) => SymbolMap::parse(&read_source(filename)).unwrap_or_else(|err| {
+            println!("invalid symbol file: {} : {}", filename, err);
+            process::exit(1);
+        }),
+        None => SymbolMap::new(),
+    };
+
+    let contents = read_source(&config.filename);
+    let (asm, errors) = disassembler::disassemble(&contents, &symbols);
+
+    print!("{}", asm);
+
+    if !errors.is_empty() {
+        println!("disassemble error: ");
+        errors.into_iter().for_each(|err| println!("  {:?}", err));
+        process::exit(1);
+    }
+}
+
+// a directory is expanded to *.asm files in it, files are linked in this order
+fn collect_files(inputs: &[String]) -> Vec<PathBuf> {
+    let files: Vec<PathBuf> = inputs
+        .iter()
+        .flat_map(|input| {
+            let path = PathBuf::from(input);
+            if !path.is_dir() {
+                return vec![path];
+            }
+
+            let mut files: Vec<PathBuf> = fs::read_dir(&path)
+                .unwrap_or_else(|err| {
+                    println!("cannot read directory: {}", err);
+                    process::exit(1);
+                })
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "asm"))
+                .collect();
+
+            // the entry `Main.asm` is placed at ROM address 0
+            files.sort_by_key(|file| {
+                (
+                    file.file_stem().is_none_or(|stem| stem != "Main"),
+                    file.clone(),
+                )
+            });
+            files
+        })
+        .collect();
+
+    if files.is_empty() {
+        println!("no *.asm files: {:?}", inputs);
+        process::exit(1);
+    }
+    files
+}
+
+// `Prog.asm` => `Prog.hack`, `dir` => `dir/dir.hack`, extension depends on the format
+fn output_filename(inputs: &[String], format: Format) -> String {
+    let input = Path::new(&inputs[0]);
+    if input.is_dir() {
+        let name = input
+            .canonicalize()
+            .ok()
+            .and_then(|dir| {
+                dir.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| String::from("out"));
+        return input
+            .join(format!("{}.{}", name, format.ext()))
+            .display()
+            .to_string();
+    }
+
+    input.with_extension(format.ext()).display().to_string()
+}
+
+// lines of the source files and included files for listing
+fn source_lines(files: &[(PathBuf, String)], nodes: &[Node]) -> SourceLines {
+    let split = |contents: &str| contents.lines().map(String::from).collect::<Vec<_>>();
+
+    let mut lines: SourceLines = match files {
+        [(_, contents)] => vec![(None, split(contents))].into_iter().collect(),
+        _ => files
+            .iter()
+            .map(|(file, contents)| (Some(file.display().to_string()), split(contents)))
+            .collect(),
+    };
+
+    nodes.iter().for_each(|node| {
+        let file = match node {
+            Node::A(a) => &a.source().file,
+            Node::C(c) => &c.source().file,
+            Node::L(l) => &l.source().file,
+        };
+        if let Some(name) = file.as_ref().filter(|_| !lines.contains_key(file)) {
+            if let Ok(contents) = fs::read_to_string(name) {
+                lines.insert(file.clone(), split(&contents));
+            }
+        }
     });
-    String::from(name)
+
+    lines
 }
 
 fn valiate_filename(filename: &str) {
@@ -83,20 +438,43 @@ fn read_source(filename: &str) -> String {
     contents
 }
 
-fn write_mcodes(filename: &str, mcodes: &str) {
-    let names = filename.rsplitn(2, ".").collect::<Vec<&str>>();
-    if let [_ext, name] = &*names {
-        let filename = format!("{}.hack", name);
-        File::create(&filename)
-            .unwrap_or_else(|err| {
-                println!("cannot open file: {}", err);
-                process::exit(1);
-            })
-            .write_all(mcodes.as_bytes())
-            .unwrap_or_else(|err| {
-                println!("cannot write file: {}", err);
+fn write_mcodes(filename: &str, mcodes: &[u8]) {
+    File::create(filename)
+        .unwrap_or_else(|err| {
+            println!("cannot open file: {}", err);
+            process::exit(1);
+        })
+        .write_all(mcodes)
+        .unwrap_or_else(|err| {
+            println!("cannot write file: {}", err);
+            process::exit(1);
+        });
+    println!("write mcodes to {}", filename);
+}
+
+fn write_map(filename: &str, format: &str, map: &SymbolMap) {
+    let name = Path::new(filename).with_extension("");
+    let name = name.display();
+    let (filename, contents) = match format {
+        "json" => (
+            format!("{}.sym.json", name),
+            map.to_json().unwrap_or_else(|err| {
+                println!("cannot serialize symbol map: {}", err);
                 process::exit(1);
-            });
-        println!("write mcodes to {}", &filename);
-    }
+            }),
+        ),
+        _ => (format!("{}.sym", name), map.to_sym()),
+    };
+
+    File::create(&filename)
+        .unwrap_or_else(|err| {
+            println!("cannot open file: {}", err);
+            process::exit(1);
+        })
+        .write_all(contents.as_bytes())
+        .unwrap_or_else(|err| {
+            println!("cannot write file: {}", err);
+            process::exit(1);
+        });
+    println!("write symbol map to {}", filename);
 }
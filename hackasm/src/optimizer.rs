@@ -0,0 +1,377 @@
+// peephole optimizer over parsed nodes, before symbols are resolved
+//
+//   push/pop pairs     @SP M=M+1 @SP AM=M-1      => @SP A=M
+//   constants          @0 D=A @X                 => D=0 @X
+//   redundant reloads  @X D=M @X M=D             => @X D=M M=D
+//   jumps to next      @L 0;JMP (L)              => (L)
+//   dead code          0;JMP @X D=M (L)          => 0;JMP (L)
+//
+// labels are never removed, addresses of nodes and labels are reassigned after the passes.
+// instructions between a label and its offset (`@TABLE+6`) are barriers, passes run on the rest.
+use super::a_command::ACommand;
+use super::c_command::{self, CCommand, Dest, Jump};
+use super::parser::{Node, Source};
+use super::symbols::{SymbolKind, Symbols};
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Default, Clone)]
+pub struct Report {
+    pub before:    usize, // instructions before optimization
+    pub after:     usize,
+    pub push_pop:  usize, // instructions saved by each pass
+    pub constants: usize,
+    pub reloads:   usize,
+    pub jumps:     usize,
+    pub dead_code: usize,
+}
+
+// removed and replaced nodes by index
+#[derive(Default)]
+struct Edit {
+    remove:  HashSet<usize>,
+    replace: HashMap<usize, Node>,
+}
+
+pub fn optimize(nodes: Vec<Node>, symbols: &mut Symbols) -> (Vec<Node>, Report) {
+    let mut report = Report {
+        before: count(&nodes),
+        ..Report::default()
+    };
+
+    let barriers = barriers(&nodes, symbols);
+    let nodes: Vec<Node> = split(nodes, &barriers)
+        .into_iter()
+        .flat_map(|(nodes, barrier)| {
+            if barrier {
+                nodes
+            } else {
+                passes(nodes, &mut report)
+            }
+        })
+        .collect();
+
+    let nodes = reassign(nodes, symbols);
+    report.after = count(&nodes);
+    (nodes, report)
+}
+
+fn passes(nodes: Vec<Node>, report: &mut Report) -> Vec<Node> {
+    let mut nodes = nodes;
+    loop {
+        let before = count(&nodes);
+
+        nodes = apply(nodes, push_pop, &mut report.push_pop);
+        nodes = apply(nodes, constants, &mut report.constants);
+        nodes = apply(nodes, reloads, &mut report.reloads);
+        nodes = apply(nodes, jumps, &mut report.jumps);
+        nodes = apply(nodes, dead_code, &mut report.dead_code);
+
+        if count(&nodes) == before {
+            return nodes;
+        }
+    }
+}
+
+// ROM addresses from a label to its offset in label-relative expressions,
+// every address if the offset is unknown before variables are allocated
+fn barriers(nodes: &[Node], symbols: &Symbols) -> HashSet<usize> {
+    let mut barriers = HashSet::new();
+
+    nodes.iter().for_each(|node| {
+        let a = match node {
+            Node::A(a) if a.expr.is_some() => a,
+            _ => return,
+        };
+        let labels: Vec<usize> = a
+            .symbols()
+            .into_iter()
+            .filter_map(|name| symbols.get(name.to_string()))
+            .filter(|sym| sym.kind == SymbolKind::Label)
+            .map(|sym| sym.addr)
+            .collect();
+        if labels.is_empty() {
+            return;
+        }
+
+        match a.eval(symbols).filter(|target| *target >= 0) {
+            Some(target) => labels.into_iter().for_each(|label| {
+                let target = target as usize;
+                barriers.extend(label.min(target)..=label.max(target));
+            }),
+            None => barriers.extend(0..=count(nodes)),
+        }
+    });
+
+    barriers
+}
+
+// consecutive nodes inside or outside of barriers
+fn split(nodes: Vec<Node>, barriers: &HashSet<usize>) -> Vec<(Vec<Node>, bool)> {
+    let mut runs: Vec<(Vec<Node>, bool)> = Vec::new();
+
+    nodes.into_iter().for_each(|node| {
+        let addr = match &node {
+            Node::A(a) => a.addr,
+            Node::C(c) => c.addr,
+            Node::L(l) => l.addr,
+        };
+        let barrier = barriers.contains(&addr);
+
+        match runs.last_mut() {
+            Some((run, b)) if *b == barrier => run.push(node),
+            _ => runs.push((vec![node], barrier)),
+        }
+    });
+
+    runs
+}
+
+fn apply(nodes: Vec<Node>, pass: fn(&[Node]) -> Edit, saved: &mut usize) -> Vec<Node> {
+    let before = count(&nodes);
+    let Edit {
+        remove,
+        mut replace,
+    } = pass(&nodes);
+
+    let nodes: Vec<Node> = nodes
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !remove.contains(i))
+        .map(|(i, node)| replace.remove(&i).unwrap_or(node))
+        .collect();
+
+    *saved += before - count(&nodes);
+    nodes
+}
+
+// `@SP M=M+1 @SP AM=M-1` => `@SP A=M`
+fn push_pop(nodes: &[Node]) -> Edit {
+    let mut edit = Edit::default();
+
+    let mut i = 0;
+    while i + 3 < nodes.len() {
+        if is_a(&nodes[i], "SP")
+            && is_c(&nodes[i + 1], Dest::M, "M+1")
+            && is_a(&nodes[i + 2], "SP")
+            && is_c(&nodes[i + 3], Dest::AM, "M-1")
+        {
+            edit.remove.insert(i + 2);
+            edit.remove.insert(i + 3);
+            edit.replace.insert(i + 1, c_node("A=M", &nodes[i + 1]));
+            i += 4;
+        } else {
+            i += 1;
+        }
+    }
+
+    edit
+}
+
+// `@0 D=A @X` => `D=0 @X`, A is overwritten by the next instruction
+fn constants(nodes: &[Node]) -> Edit {
+    let mut edit = Edit::default();
+
+    let mut i = 0;
+    while i + 2 < nodes.len() {
+        let value = match &nodes[i] {
+            Node::A(a) if a.symbols().is_empty() && (a.value == 0 || a.value == 1) => a.value,
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+
+        if is_c(&nodes[i + 1], Dest::D, "A") && matches!(nodes[i + 2], Node::A(_)) {
+            edit.remove.insert(i);
+            edit.replace
+                .insert(i + 1, c_node(&format!("D={}", value), &nodes[i + 1]));
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+
+    edit
+}
+
+// `@X` when A already holds X
+fn reloads(nodes: &[Node]) -> Edit {
+    let mut edit = Edit::default();
+    let mut loaded: Option<&ACommand> = None;
+
+    nodes.iter().enumerate().for_each(|(i, node)| match node {
+        Node::A(a) => match loaded {
+            Some(prev) if same_operand(prev, a) => {
+                edit.remove.insert(i);
+            }
+            _ => loaded = Some(a),
+        },
+        Node::C(c) if writes_a(c) => loaded = None,
+        Node::C(_) => {}
+        // jumps may come from anywhere
+        Node::L(_) => loaded = None,
+    });
+
+    edit
+}
+
+// `@L D;JGT (L) @X` => `(L) @X`
+fn jumps(nodes: &[Node]) -> Edit {
+    let mut edit = Edit::default();
+
+    let mut i = 0;
+    while i + 2 < nodes.len() {
+        let target = match (&nodes[i], &nodes[i + 1]) {
+            (Node::A(a), Node::C(c)) if c.jump != Jump::Null && c.dest == Dest::Null => {
+                a.symbol_name.as_deref()
+            }
+            _ => None,
+        };
+
+        let target = match target {
+            Some(target) => target,
+            None => {
+                i += 1;
+                continue;
+            }
+        };
+
+        // labels right after the jump, and A is loaded again after them
+        let labels: Vec<&str> = nodes[i + 2..]
+            .iter()
+            .map_while(|node| match node {
+                Node::L(l) => Some(l.symbol.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        let next = nodes.get(i + 2 + labels.len());
+
+        if labels.contains(&target) && matches!(next, None | Some(Node::A(_))) {
+            edit.remove.insert(i);
+            edit.remove.insert(i + 1);
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+
+    edit
+}
+
+// instructions after an unconditional jump until the next label
+fn dead_code(nodes: &[Node]) -> Edit {
+    let mut edit = Edit::default();
+    let mut dead = false;
+
+    nodes.iter().enumerate().for_each(|(i, node)| match node {
+        Node::L(_) => dead = false,
+        _ if dead => {
+            edit.remove.insert(i);
+        }
+        Node::C(c) if is_unconditional(c) => dead = true,
+        _ => {}
+    });
+
+    edit
+}
+
+// `0;JMP`, also `0;JEQ`, `1;JNE`...
+fn is_unconditional(c: &CCommand) -> bool {
+    match c.comp.exp() {
+        "0" => matches!(c.jump, Jump::JEQ | Jump::JGE | Jump::JLE | Jump::JMP),
+        "1" => matches!(c.jump, Jump::JGT | Jump::JGE | Jump::JNE | Jump::JMP),
+        "-1" => matches!(c.jump, Jump::JLT | Jump::JLE | Jump::JNE | Jump::JMP),
+        _ => c.jump == Jump::JMP,
+    }
+}
+
+fn writes_a(c: &CCommand) -> bool {
+    matches!(c.dest, Dest::A | Dest::AM | Dest::AD | Dest::AMD)
+}
+
+fn same_operand(a: &ACommand, b: &ACommand) -> bool {
+    match (&a.symbol_name, &b.symbol_name) {
+        (Some(x), Some(y)) => x == y,
+        (None, None) => a.expr == b.expr && a.value == b.value,
+        _ => false,
+    }
+}
+
+fn is_a(node: &Node, name: &str) -> bool {
+    matches!(node, Node::A(a) if a.symbol_name.as_deref() == Some(name))
+}
+
+fn is_c(node: &Node, dest: Dest, exp: &str) -> bool {
+    matches!(node, Node::C(c) if c.dest == dest && c.comp.exp() == exp && c.jump == Jump::Null)
+}
+
+// new C-Command at the source of the node
+fn c_node(code: &str, node: &Node) -> Node {
+    let source = match node {
+        Node::A(a) => a.source(),
+        Node::C(c) => c.source(),
+        Node::L(l) => l.source(),
+    };
+    let source = Source {
+        code: code.to_string(),
+        ..source.clone()
+    };
+    Node::C(c_command::parse(0, source).expect("valid C-Command"))
+}
+
+fn count(nodes: &[Node]) -> usize {
+    nodes
+        .iter()
+        .filter(|node| !matches!(node, Node::L(_)))
+        .count()
+}
+
+// assign ROM addresses again and update labels in symbols
+fn reassign(nodes: Vec<Node>, symbols: &mut Symbols) -> Vec<Node> {
+    let mut addr = 0;
+
+    nodes
+        .into_iter()
+        .map(|node| match node {
+            Node::A(mut a) => {
+                a.addr = addr;
+                addr += 1;
+                Node::A(a)
+            }
+            Node::C(mut c) => {
+                c.addr = addr;
+                addr += 1;
+                Node::C(c)
+            }
+            Node::L(mut l) => {
+                l.addr = addr;
+                l.symbol.addr = addr;
+                symbols.add(l.symbol.clone());
+                Node::L(l)
+            }
+        })
+        .collect()
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let saved = self.before - self.after;
+        let percent = match self.before {
+            0 => 0.0,
+            n => saved as f64 * 100.0 / n as f64,
+        };
+
+        writeln!(
+            f,
+            "optimized {} => {} instructions, saved {} ({:.1}%)",
+            self.before, self.after, saved, percent
+        )?;
+        writeln!(f, "  push/pop pairs    : {}", self.push_pop)?;
+        writeln!(f, "  constants         : {}", self.constants)?;
+        writeln!(f, "  redundant reloads : {}", self.reloads)?;
+        writeln!(f, "  jumps to next     : {}", self.jumps)?;
+        write!(f, "  dead code         : {}", self.dead_code)
+    }
+}
@@ -0,0 +1,116 @@
+// output formats of machine codes
+//
+//   text     `0101...` lines (*.hack)
+//   bin      raw big-endian 16bit words
+//   hex      Intel HEX, 2 bytes per word
+//   logisim  Logisim / Digital ROM image (`v2.0 raw`)
+//   lst      listing with addresses, encoded words and cross-references
+use super::codegen;
+use super::listing::{self, SourceLines};
+use super::parser::Node;
+
+use anyhow::{anyhow, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Binary,
+    IntelHex,
+    Logisim,
+    Listing,
+}
+
+// bytes per Intel HEX data record
+const HEX_RECORD_LEN: usize = 16;
+
+// words per line of Logisim image
+const LOGISIM_LINE_LEN: usize = 8;
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Format> {
+        match name {
+            "text" => Ok(Format::Text),
+            "bin" => Ok(Format::Binary),
+            "hex" => Ok(Format::IntelHex),
+            "logisim" => Ok(Format::Logisim),
+            "lst" => Ok(Format::Listing),
+            _ => Err(anyhow!(
+                "unknown format {}, expected to text, bin, hex, logisim or lst",
+                name
+            )),
+        }
+    }
+
+    // extension of the output file
+    pub fn ext(&self) -> &'static str {
+        match self {
+            Format::Text => "hack",
+            Format::Binary => "bin",
+            Format::IntelHex => "hex",
+            Format::Logisim => "rom",
+            Format::Listing => "lst",
+        }
+    }
+}
+
+pub fn emit(nodes: &[Node], format: Format) -> Vec<u8> {
+    let words = codegen::words(nodes);
+
+    match format {
+        Format::Text => text(&words).into_bytes(),
+        Format::Binary => binary(&words),
+        Format::IntelHex => intel_hex(&words).into_bytes(),
+        Format::Logisim => logisim(&words).into_bytes(),
+        Format::Listing => listing::generate(nodes, &SourceLines::new()).into_bytes(),
+    }
+}
+
+pub fn text(words: &[u16]) -> String {
+    words
+        .iter()
+        .map(|word| format!("{:016b}\n", word))
+        .collect()
+}
+
+pub fn binary(words: &[u16]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_be_bytes()).collect()
+}
+
+// `:LLAAAATT DD... CC`, data records and the end of file record
+pub fn intel_hex(words: &[u16]) -> String {
+    let bytes = binary(words);
+    let mut lines: Vec<String> = bytes
+        .chunks(HEX_RECORD_LEN)
+        .enumerate()
+        .map(|(n, data)| hex_record((n * HEX_RECORD_LEN) as u16, 0x00, data))
+        .collect();
+    lines.push(hex_record(0, 0x01, &[]));
+
+    lines.join("\n") + "\n"
+}
+
+fn hex_record(addr: u16, kind: u8, data: &[u8]) -> String {
+    let mut record = vec![data.len() as u8];
+    record.extend(&addr.to_be_bytes());
+    record.push(kind);
+    record.extend(data);
+
+    let sum = record.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    record.push(sum.wrapping_neg());
+
+    let hex: String = record.iter().map(|b| format!("{:02X}", b)).collect();
+    format!(":{}", hex)
+}
+
+pub fn logisim(words: &[u16]) -> String {
+    let mut lines = vec![String::from("v2.0 raw")];
+    lines.extend(words.chunks(LOGISIM_LINE_LEN).map(|chunk| {
+        chunk
+            .iter()
+            .map(|word| format!("{:04x}", word))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }));
+
+    lines.join("\n") + "\n"
+}
@@ -1,10 +1,14 @@
 // parser
 use super::a_command::*;
 use super::c_command::*;
+use super::error::AsmError;
 use super::l_command::*;
+use super::preprocessor::Preprocessor;
 use super::symbols::Symbols;
 
-use anyhow::Result;
+use serde::Serialize;
+
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub enum Node {
@@ -13,22 +17,94 @@ pub enum Node {
     L(LCommand),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Source {
-    pub line: usize,  // line number in source file
-    pub code: String, // original source code
+    pub file: Option<String>, // included file, `None` for the main file
+    pub line: usize,          // line number in source file
+    pub code: String,         // original source code
 }
 
-pub type ParseResult = (Vec<Node>, Vec<anyhow::Error>);
+pub type ParseResult = (Vec<Node>, Vec<AsmError>);
 
-const COMMENT: &'static str = "//";
+const COMMENT: &str = "//";
 
 pub fn parse(contents: &str, symbols: &mut Symbols) -> ParseResult {
-    let sources = parse_lines(contents);
-    parse_sources(sources, symbols)
+    parse_with_dir(contents, Path::new("."), Dialect::Strict, symbols)
+}
+
+// `.include` paths are relative to `dir`
+pub fn parse_with_dir(
+    contents: &str,
+    dir: &Path,
+    dialect: Dialect,
+    symbols: &mut Symbols,
+) -> ParseResult {
+    parse_unit(contents, None, dir, 0, dialect, symbols)
+}
+
+// assemble files into one program, ROM addresses continue over files,
+// variables share the RAM counter of `symbols` and labels starting with `.` are local to each file
+pub fn parse_files(
+    files: &[(PathBuf, String)],
+    dialect: Dialect,
+    symbols: &mut Symbols,
+) -> ParseResult {
+    let mut nodes = Vec::new();
+    let mut errors = Vec::new();
+
+    files.iter().for_each(|(path, contents)| {
+        let file = path.display().to_string();
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let addr = nodes
+            .iter()
+            .filter(|node| !matches!(node, Node::L(_)))
+            .count();
+
+        let (unit, unit_errors) = parse_unit(contents, Some(&file), dir, addr, dialect, symbols);
+        nodes.extend(unit);
+        errors.extend(unit_errors);
+    });
+
+    (nodes, errors)
 }
 
-fn parse_lines(contents: &str) -> Vec<Source> {
+// parse a file from ROM address `addr`, `file` is `None` for a single main file
+fn parse_unit(
+    contents: &str,
+    file: Option<&str>,
+    dir: &Path,
+    addr: usize,
+    dialect: Dialect,
+    symbols: &mut Symbols,
+) -> ParseResult {
+    let mut preprocessor = Preprocessor::new();
+    let sources = preprocessor.run(contents, file, dir);
+
+    let sources = sources
+        .into_iter()
+        .map(|source| Source {
+            code: strip_whitespaces(&source.code),
+            ..source
+        })
+        .collect();
+    let scope = file
+        .and_then(|file| Path::new(file).file_stem())
+        .map(|stem| stem.to_string_lossy().to_string());
+    let (nodes, errors) = parse_sources(sources, addr, scope.as_deref(), dialect, symbols);
+
+    // locate errors in the original lines
+    let mut errors: Vec<AsmError> = preprocessor.errors.drain(..).chain(errors).collect();
+    errors.iter_mut().for_each(|err| {
+        if let Some(text) = preprocessor.line(&err.file, err.line) {
+            err.locate(text)
+        }
+    });
+
+    (nodes, errors)
+}
+
+// split contents into lines without comments, whitespaces are kept for directives
+pub fn parse_lines(contents: &str, file: Option<&str>) -> Vec<Source> {
     contents
         .lines()
         .enumerate()
@@ -37,10 +113,13 @@ fn parse_lines(contents: &str) -> Vec<Source> {
             let code = drop_whitespaces(content);
             let (code, _comment) = split_code(&code, COMMENT, false);
 
-            code.filter(|c| c.len() > 0).map(|c| Source {
-                line: line + 1,
-                code: truncate_whitespaces(c),
-            })
+            code.map(|c| c.trim_end())
+                .filter(|c| !c.is_empty())
+                .map(|c| Source {
+                    file: file.map(String::from),
+                    line: line + 1,
+                    code: c.to_string(),
+                })
         })
         .collect()
 }
@@ -52,16 +131,22 @@ fn drop_whitespaces(content: &str) -> String {
         .collect::<String>()
 }
 
-fn parse_sources(sources: Vec<Source>, symbols: &mut Symbols) -> ParseResult {
+fn parse_sources(
+    sources: Vec<Source>,
+    addr: usize,
+    scope: Option<&str>,
+    dialect: Dialect,
+    symbols: &mut Symbols,
+) -> ParseResult {
     let mut nodes = Vec::new();
     let mut errors = Vec::new();
 
-    let mut addr = 0;
+    let mut addr = addr;
 
     sources.into_iter().for_each(|source| {
-        match instrument(source, addr) {
+        match instrument(source, addr, dialect).map(|node| localize(node, scope)) {
             Ok(Node::L(l)) => {
-                // TODO: check duplicate symbol definition
+                // duplicate definitions are reported by validator
                 symbols.add(l.symbol.clone());
                 nodes.push(Node::L(l))
             }
@@ -76,25 +161,63 @@ fn parse_sources(sources: Vec<Source>, symbols: &mut Symbols) -> ParseResult {
     (nodes, errors)
 }
 
-fn instrument(source: Source, addr: usize) -> Result<Node> {
+fn instrument(source: Source, addr: usize, dialect: Dialect) -> Result<Node, AsmError> {
     // A-Command @foo
     if source.code.starts_with("@") {
-        return super::a_command::parse(addr, source).map(|a| Node::A(a));
+        return super::a_command::parse(addr, source).map(Node::A);
     }
 
     // L-Command (LABEL)
     if source.code.starts_with("(") {
-        return super::l_command::parse(addr, source).map(|l| Node::L(l));
+        return super::l_command::parse(addr, source).map(Node::L);
     }
 
     // C-Command dest=comp;jmp
-    super::c_command::parse(addr, source).map(|c| Node::C(c))
+    super::c_command::parse_with_dialect(addr, source, dialect).map(Node::C)
 }
 
-fn truncate_whitespaces(code: &str) -> String {
-    code.chars()
-        .filter(|c| !c.is_whitespace())
-        .collect::<String>()
+// `@.loop` and `(.loop)` in Main.asm => `@Main:.loop` and `(Main:.loop)`
+pub fn localize(node: Node, scope: Option<&str>) -> Node {
+    let scope = match scope {
+        Some(scope) => scope,
+        None => return node,
+    };
+    let local = |name: &str| name.starts_with('.');
+
+    match node {
+        Node::A(mut a) => {
+            if let Some(name) = a.symbol_name.as_mut().filter(|name| local(name)) {
+                *name = format!("{}:{}", scope, name);
+            }
+            if let Some(expr) = a.expr.as_mut() {
+                expr.rename(&|name: &str| local(name).then(|| format!("{}:{}", scope, name)));
+            }
+            Node::A(a)
+        }
+        Node::L(mut l) if local(&l.symbol.name) => {
+            l.symbol.name = format!("{}:{}", scope, l.symbol.name);
+            Node::L(l)
+        }
+        _ => node,
+    }
+}
+
+// code without whitespaces, the space of the character literal `' '` is kept
+pub fn strip_whitespaces(code: &str) -> String {
+    code_chars(code).into_iter().map(|(_, c)| c).collect()
+}
+
+// characters of `strip_whitespaces` with their index in code
+pub fn code_chars(code: &str) -> Vec<(usize, char)> {
+    let chars: Vec<char> = code.chars().collect();
+    let quoted = |i: usize| i > 0 && chars[i - 1] == '\'' && chars.get(i + 1) == Some(&'\'');
+
+    chars
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| !c.is_whitespace() || quoted(*i))
+        .map(|(i, c)| (i, *c))
+        .collect()
 }
 
 pub fn split_code<'a>(
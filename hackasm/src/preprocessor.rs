@@ -0,0 +1,335 @@
+// preprocessor, expands directives before instrument
+//
+//   .define NAME value         replace NAME in following lines
+//   .macro NAME a, b           define macro with parameters
+//   .endm
+//   NAME x, y                  expand macro
+//   .include "file.asm"        relative to the including file
+//   .if NAME / .if !NAME       NAME is defined and not 0
+//   .if NAME == value          also `!=`
+//   .else
+//   .endif
+//
+// expanded lines keep the file and line of the macro call, so errors and
+// source maps point to the original files.
+use super::a_command::is_valid_symbol;
+use super::error::{AsmError, ErrorKind};
+use super::parser::{parse_lines, strip_whitespaces, Source};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// max depth of nested macros and includes
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone)]
+struct Macro {
+    params: Vec<String>,
+    body:   Vec<Source>,
+}
+
+// `.if` block
+struct Cond {
+    parent: bool, // enclosing block is active
+    taken:  bool, // current branch is active
+    else_:  bool, // in `.else` branch
+    source: Source,
+}
+
+#[derive(Default)]
+pub struct Preprocessor {
+    defines:    HashMap<String, String>,
+    macros:     HashMap<String, Macro>,
+    lines:      HashMap<Option<String>, Vec<String>>, // original lines of each file
+    depth:      usize,
+    pub errors: Vec<AsmError>,
+}
+
+impl Preprocessor {
+    pub fn new() -> Preprocessor {
+        Preprocessor::default()
+    }
+
+    // preprocess contents of `file` (`None` for the main file) in `dir`
+    pub fn run(&mut self, contents: &str, file: Option<&str>, dir: &Path) -> Vec<Source> {
+        self.lines.insert(
+            file.map(String::from),
+            contents.lines().map(String::from).collect(),
+        );
+
+        let sources = parse_lines(contents, file);
+        self.expand(sources, dir)
+    }
+
+    // original line of the file
+    pub fn line(&self, file: &Option<String>, line: usize) -> Option<&str> {
+        self.lines
+            .get(file)
+            .and_then(|lines| lines.get(line.wrapping_sub(1)))
+            .map(|line| line.as_str())
+    }
+
+    fn expand(&mut self, sources: Vec<Source>, dir: &Path) -> Vec<Source> {
+        let mut expanded = Vec::new();
+        let mut conds: Vec<Cond> = Vec::new();
+        let mut sources = sources.into_iter();
+
+        while let Some(source) = sources.next() {
+            let active = conds.last().is_none_or(|c| c.parent && c.taken);
+            let (directive, rest) = split_directive(&source.code);
+
+            match directive {
+                ".if" => {
+                    let taken = active && self.cond(&source, rest);
+                    conds.push(Cond {
+                        parent: active,
+                        taken,
+                        else_: false,
+                        source,
+                    });
+                }
+                ".else" => match conds.last_mut() {
+                    Some(cond) if !cond.else_ => {
+                        cond.taken = !cond.taken;
+                        cond.else_ = true;
+                    }
+                    _ => self.error(ErrorKind::UnbalancedDirective, &source, 0, ".else"),
+                },
+                ".endif" => {
+                    if conds.pop().is_none() {
+                        self.error(ErrorKind::UnbalancedDirective, &source, 0, ".endif");
+                    }
+                }
+                _ if !active => {}
+                ".define" => self.define(&source, rest),
+                ".macro" => {
+                    let body = collect_macro(&mut sources);
+                    match body {
+                        Some(body) => self.define_macro(&source, rest, body),
+                        None => self.error(ErrorKind::UnbalancedDirective, &source, 0, ".macro"),
+                    }
+                }
+                ".include" => {
+                    let included = self.include(&source, rest, dir);
+                    expanded.extend(included);
+                }
+                _ if directive.starts_with('.') => {
+                    self.error(ErrorKind::UnknownDirective, &source, 0, directive)
+                }
+                _ if self.macros.contains_key(directive) => {
+                    let lines = self.call(&source, directive, rest);
+                    if self.enter(&source, directive) {
+                        let lines = self.expand(lines, dir);
+                        expanded.extend(lines);
+                        self.depth -= 1;
+                    }
+                }
+                _ => expanded.push(Source {
+                    code: self.substitute(&source.code, &self.defines),
+                    ..source
+                }),
+            }
+        }
+
+        conds.iter().for_each(|cond| {
+            let source = cond.source.clone();
+            self.error(ErrorKind::UnbalancedDirective, &source, 0, ".if")
+        });
+
+        expanded
+    }
+
+    // `.define NAME value`
+    fn define(&mut self, source: &Source, rest: &str) {
+        let (name, value) = split_directive(rest);
+        if !is_valid_symbol(name) || value.is_empty() {
+            let offset = offset(&source.code, rest);
+            return self.error(ErrorKind::InvalidDirective, source, offset, rest);
+        }
+
+        let value = self.substitute(value, &self.defines);
+        self.defines.insert(name.to_string(), value);
+    }
+
+    // `.macro NAME a, b`
+    fn define_macro(&mut self, source: &Source, rest: &str, body: Vec<Source>) {
+        let (name, params) = split_directive(rest);
+        let params = split_args(params);
+
+        if !is_valid_symbol(name) || !params.iter().all(|param| is_valid_symbol(param)) {
+            let offset = offset(&source.code, rest);
+            return self.error(ErrorKind::InvalidDirective, source, offset, rest);
+        }
+
+        self.macros.insert(name.to_string(), Macro { params, body });
+    }
+
+    // body of the macro with arguments, at the line of the call
+    fn call(&mut self, source: &Source, name: &str, rest: &str) -> Vec<Source> {
+        let mac = self.macros[name].clone();
+        let args = split_args(rest);
+
+        if args.len() != mac.params.len() {
+            let note = format!("expected {}, found {}", mac.params.len(), args.len());
+            let err = AsmError::new(ErrorKind::MacroArguments, source, 0, name).note(&note);
+            self.errors.push(err);
+            return vec![];
+        }
+
+        let bindings: HashMap<String, String> = mac.params.into_iter().zip(args).collect();
+
+        mac.body
+            .iter()
+            .map(|line| Source {
+                file: source.file.clone(),
+                line: source.line,
+                code: self.substitute(&line.code, &bindings),
+            })
+            .collect()
+    }
+
+    // `.include "file.asm"`
+    fn include(&mut self, source: &Source, rest: &str, dir: &Path) -> Vec<Source> {
+        let offset = offset(&source.code, rest);
+        let name = match rest.strip_prefix('"').and_then(|r| r.strip_suffix('"')) {
+            Some(name) if !name.is_empty() => name,
+            _ => {
+                self.error(ErrorKind::InvalidDirective, source, offset, rest);
+                return vec![];
+            }
+        };
+
+        let path = dir.join(name);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                let err = AsmError::new(ErrorKind::InvalidInclude, source, offset, rest)
+                    .note(&err.to_string());
+                self.errors.push(err);
+                return vec![];
+            }
+        };
+
+        if !self.enter(source, rest) {
+            return vec![];
+        }
+
+        let file = path.display().to_string();
+        let dir = path
+            .parent()
+            .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+        let sources = self.run(&contents, Some(&file), &dir);
+
+        self.depth -= 1;
+        sources
+    }
+
+    // `.if NAME`, `.if !NAME`, `.if NAME == value`, `.if NAME != value`
+    fn cond(&mut self, source: &Source, rest: &str) -> bool {
+        if let Some((lhs, rhs)) = rest.split_once("==") {
+            return self.substitute(lhs.trim(), &self.defines)
+                == self.substitute(rhs.trim(), &self.defines);
+        }
+        if let Some((lhs, rhs)) = rest.split_once("!=") {
+            return self.substitute(lhs.trim(), &self.defines)
+                != self.substitute(rhs.trim(), &self.defines);
+        }
+
+        let (negate, name) = match rest.strip_prefix('!') {
+            Some(name) => (true, name.trim()),
+            None => (false, rest),
+        };
+        if !is_valid_symbol(name) {
+            let offset = offset(&source.code, rest);
+            self.error(ErrorKind::InvalidDirective, source, offset, rest);
+            return false;
+        }
+
+        let defined = self.defines.get(name).is_some_and(|value| value != "0");
+        defined != negate
+    }
+
+    // count nested macros and includes
+    fn enter(&mut self, source: &Source, operand: &str) -> bool {
+        if self.depth >= MAX_DEPTH {
+            let offset = offset(&source.code, operand);
+            let err = AsmError::new(ErrorKind::InvalidInclude, source, offset, operand)
+                .note("nested too deeply");
+            self.errors.push(err);
+            return false;
+        }
+        self.depth += 1;
+        true
+    }
+
+    // replace symbols in code with bindings
+    fn substitute(&self, code: &str, bindings: &HashMap<String, String>) -> String {
+        let mut result = String::new();
+        let mut symbol = String::new();
+
+        for c in code.chars().chain(std::iter::once('\n')) {
+            if is_symbol_char(c) {
+                symbol.push(c);
+                continue;
+            }
+            match bindings.get(&symbol) {
+                Some(value) => result.push_str(value),
+                None => result.push_str(&symbol),
+            }
+            symbol.clear();
+            if c != '\n' {
+                result.push(c);
+            }
+        }
+
+        result
+    }
+
+    fn error(&mut self, kind: ErrorKind, source: &Source, offset: usize, operand: &str) {
+        self.errors
+            .push(AsmError::new(kind, source, offset, operand));
+    }
+}
+
+// lines until `.endm`, `None` if it is missing
+fn collect_macro(sources: &mut impl Iterator<Item = Source>) -> Option<Vec<Source>> {
+    let mut body = Vec::new();
+    for source in sources {
+        if split_directive(&source.code).0 == ".endm" {
+            return Some(body);
+        }
+        body.push(source);
+    }
+    None
+}
+
+// `.define NAME value` => (".define", "NAME value")
+fn split_directive(code: &str) -> (&str, &str) {
+    match code.split_once(char::is_whitespace) {
+        Some((directive, rest)) => (directive, rest.trim()),
+        None => (code, ""),
+    }
+}
+
+// `a, b` => ["a", "b"]
+fn split_args(args: &str) -> Vec<String> {
+    args.split(',')
+        .map(|arg| arg.trim().to_string())
+        .filter(|arg| !arg.is_empty())
+        .collect()
+}
+
+// offset of `rest` at the end of code, in code without whitespaces
+fn offset(code: &str, rest: &str) -> usize {
+    if !code.ends_with(rest) {
+        return 0;
+    }
+    strip_whitespaces(&code[..code.len() - rest.len()])
+        .chars()
+        .count()
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$' || c == ':'
+}
@@ -0,0 +1,295 @@
+// interactive assembler, `hackasm repl`
+//
+//   > :exec
+//   exec on
+//   > @5
+//   0000  0000000000000101  @5              A=5
+//   > D=A
+//   0001  1110110000010000  D=A             D=5
+//
+// labels and variables are kept in a running symbol table,
+// instructions are executed against A, D and RAM while `:exec` is on
+use super::a_command;
+use super::c_command::{self, CCommand, Dest, Dialect, Jump};
+use super::codegen;
+use super::error::AsmError;
+use super::l_command;
+use super::parser::{self, Node, Source};
+use super::symbols::{SymbolKind, Symbols};
+use super::validator;
+
+use std::io::{self, BufRead, Write};
+
+const PROMPT: &str = "> ";
+
+const RAM_SIZE: usize = 0x8000;
+
+const HELP: &str = "\
+instructions   @value, @symbol, dest=comp;jump, (LABEL)
+:exec          toggle execution against A, D and RAM
+:regs          print A, D and the next ROM address
+:ram ADDR [N]  print N words of RAM from ADDR
+:set R VALUE   set A, D or RAM[R] (address or symbol)
+:symbols       print labels and variables
+:reset         clear registers, RAM and symbols
+:help          print this help
+:quit          exit";
+
+pub struct Repl {
+    dialect: Dialect,
+    symbols: Symbols,
+    exec:    bool,
+    line:    usize,
+    pc:      usize, // ROM address of the next instruction
+    a:       u16,
+    d:       u16,
+    ram:     Vec<u16>,
+}
+
+impl Repl {
+    pub fn new(dialect: Dialect) -> Repl {
+        Repl {
+            dialect,
+            symbols: Symbols::new(),
+            exec: false,
+            line: 0,
+            pc: 0,
+            a: 0,
+            d: 0,
+            ram: vec![0; RAM_SIZE],
+        }
+    }
+
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, output: &mut W) -> io::Result<()> {
+        writeln!(output, "hackasm repl, :help for commands")?;
+        write!(output, "{}", PROMPT)?;
+        output.flush()?;
+
+        for line in input.lines() {
+            let line = line?;
+            match self.eval(&line) {
+                Some(result) if !result.is_empty() => writeln!(output, "{}", result)?,
+                Some(_) => {}
+                None => return Ok(()),
+            }
+            write!(output, "{}", PROMPT)?;
+            output.flush()?;
+        }
+
+        writeln!(output)
+    }
+
+    // result of one input line, `None` to quit
+    pub fn eval(&mut self, input: &str) -> Option<String> {
+        self.line += 1;
+
+        let (code, _comment) = parser::split_code(input, "//", false);
+        let code = parser::strip_whitespaces(code.unwrap_or(""));
+
+        if code.is_empty() {
+            return Some(String::new());
+        }
+        if code.starts_with(':') {
+            return self.command(input.trim());
+        }
+
+        let source = Source {
+            file: None,
+            line: self.line,
+            code,
+        };
+        Some(self.instruction(source).unwrap_or_else(|err| err))
+    }
+
+    fn instruction(&mut self, source: Source) -> Result<String, String> {
+        if source.code.starts_with('(') {
+            let l = l_command::parse(self.pc, source).map_err(render)?;
+            let result = format!("({}) = {}", l.symbol.name, l.symbol.addr);
+            self.symbols.add(l.symbol);
+            return Ok(result);
+        }
+
+        let node = if source.code.starts_with('@') {
+            Node::A(a_command::parse(self.pc, source).map_err(render)?)
+        } else {
+            Node::C(c_command::parse_with_dialect(self.pc, source, self.dialect).map_err(render)?)
+        };
+
+        // labels defined later are variables here
+        let node = self.symbols.resolve(vec![node]).pop().unwrap();
+        if let Node::A(a) = &node {
+            if let Some(diagnostic) = validator::check_value(a, &self.symbols) {
+                return Err(format!("error: {}", diagnostic.message));
+            }
+        }
+        let code = match &node {
+            Node::A(a) => a.source().code.clone(),
+            Node::C(c) => c.source().code.clone(),
+            Node::L(_) => String::new(),
+        };
+        let word =
+            codegen::encode(&node).ok_or_else(|| format!("error: no machine code for {}", code))?;
+        let listing = format!("{:04X}  {:016b}  {}", self.pc, word, code);
+        self.pc += 1;
+
+        if !self.exec {
+            return Ok(listing);
+        }
+
+        let state = match &node {
+            // A is loaded with the word in the listing
+            Node::A(_) => {
+                self.a = word;
+                format!("A={}", self.a as i16)
+            }
+            Node::C(c) => self.execute(c),
+            Node::L(_) => String::new(),
+        };
+        Ok(format!("{:40}{}", listing, state).trim_end().to_string())
+    }
+
+    // execute C-Command and describe changed registers
+    fn execute(&mut self, c: &CCommand) -> String {
+        let addr = self.a as usize % RAM_SIZE;
+        let out = compute(c, self.d, self.a, self.ram[addr]);
+
+        let mut state = Vec::new();
+        if matches!(c.dest, Dest::M | Dest::MD | Dest::AM | Dest::AMD) {
+            self.ram[addr] = out;
+            state.push(format!("RAM[{}]={}", addr, out as i16));
+        }
+        if matches!(c.dest, Dest::A | Dest::AM | Dest::AD | Dest::AMD) {
+            self.a = out;
+            state.push(format!("A={}", out as i16));
+        }
+        if matches!(c.dest, Dest::D | Dest::MD | Dest::AD | Dest::AMD) {
+            self.d = out;
+            state.push(format!("D={}", out as i16));
+        }
+        if jumps(c.jump, out as i16) {
+            state.push(format!("jump to ROM[{}]", self.a));
+        }
+
+        state.join(" ")
+    }
+
+    fn command(&mut self, input: &str) -> Option<String> {
+        let args: Vec<&str> = input.split_whitespace().collect();
+        let result = match &args[..] {
+            [":quit"] | [":q"] => return None,
+            [":help"] => HELP.to_string(),
+            [":exec"] => {
+                self.exec = !self.exec;
+                format!("exec {}", if self.exec { "on" } else { "off" })
+            }
+            [":regs"] => self.regs(),
+            [":ram", addr] => self.dump(addr, "1"),
+            [":ram", addr, len] => self.dump(addr, len),
+            [":set", reg, value] => self.set(reg, value),
+            [":symbols"] => self.list_symbols(),
+            [":reset"] => {
+                *self = Repl::new(self.dialect);
+                String::from("reset")
+            }
+            _ => format!("unknown command: {}, :help for commands", input),
+        };
+        Some(result)
+    }
+
+    fn regs(&self) -> String {
+        format!("A={} D={} PC={}", self.a as i16, self.d as i16, self.pc)
+    }
+
+    fn dump(&mut self, addr: &str, len: &str) -> String {
+        let (addr, len) = match (self.address(addr), len.parse::<usize>()) {
+            (Some(addr), Ok(len)) => (addr, len),
+            _ => return format!("invalid address or length: {} {}", addr, len),
+        };
+
+        (addr..addr.saturating_add(len).min(RAM_SIZE))
+            .map(|addr| format!("RAM[{}]={}", addr, self.ram[addr] as i16))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn set(&mut self, reg: &str, value: &str) -> String {
+        let value = match value.parse::<i32>() {
+            Ok(value) if (-32768..=65535).contains(&value) => value as u16,
+            _ => return format!("invalid value: {}", value),
+        };
+
+        match reg {
+            "A" => self.a = value,
+            "D" => self.d = value,
+            _ => match self.address(reg) {
+                Some(addr) => self.ram[addr] = value,
+                None => return format!("invalid register: {}", reg),
+            },
+        }
+        self.regs()
+    }
+
+    // `100` or a symbol, undefined symbols are assigned as variables
+    fn address(&mut self, name: &str) -> Option<usize> {
+        let addr = match name.parse::<usize>() {
+            Ok(addr) => addr,
+            Err(_) if a_command::is_valid_symbol(name) => self.symbols.get_or_assign(name).addr,
+            Err(_) => return None,
+        };
+        Some(addr).filter(|addr| *addr < RAM_SIZE)
+    }
+
+    fn list_symbols(&self) -> String {
+        [SymbolKind::Label, SymbolKind::Variable]
+            .iter()
+            .flat_map(|kind| self.symbols.symbols(*kind))
+            .map(|sym| format!("{:?} {} = {}", sym.kind, sym.name, sym.addr))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn render(err: AsmError) -> String {
+    format!("error: {}", err.message)
+}
+
+// ALU output, x is D-register and y is A-register or M
+fn compute(c: &CCommand, d: u16, a: u16, m: u16) -> u16 {
+    let y = if c.comp.mcode & 0b1000000 != 0 { m } else { a };
+
+    // shift instructions of the extended dialect
+    if c.comp.prefix == 0b101 {
+        let operand = if c.comp.mcode & 0b0010000 != 0 { d } else { y };
+        return if c.comp.mcode & 0b0100000 != 0 {
+            operand << 1
+        } else {
+            operand >> 1
+        };
+    }
+
+    let bit = |n: u8| c.comp.mcode & (1 << n) != 0;
+    let x = if bit(5) { 0 } else { d };
+    let x = if bit(4) { !x } else { x };
+    let y = if bit(3) { 0 } else { y };
+    let y = if bit(2) { !y } else { y };
+    let out = if bit(1) { x.wrapping_add(y) } else { x & y };
+
+    if bit(0) {
+        !out
+    } else {
+        out
+    }
+}
+
+fn jumps(jump: Jump, out: i16) -> bool {
+    match jump {
+        Jump::Null => false,
+        Jump::JGT => out > 0,
+        Jump::JEQ => out == 0,
+        Jump::JGE => out >= 0,
+        Jump::JLT => out < 0,
+        Jump::JNE => out != 0,
+        Jump::JLE => out <= 0,
+        Jump::JMP => true,
+    }
+}
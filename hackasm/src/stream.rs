@@ -0,0 +1,219 @@
+// two-pass streaming assembler for large inputs
+//
+//   pass 1  read lines, record addresses of labels
+//   pass 2  read lines again, write machine words to the buffered writer
+//
+// memory is bounded by the symbol table, directives, optimization and listing are not supported
+use super::a_command;
+use super::assembler::Diagnostics;
+use super::assembler::Options;
+use super::c_command;
+use super::codegen;
+use super::error::{AsmError, ErrorKind};
+use super::l_command;
+use super::layout::{ram_overflow, rom_overflow, ROM_SIZE};
+use super::output::Format;
+use super::parser::{self, Node, Source};
+use super::symbols::Symbols;
+use super::validator::{self, Diagnostic, Labels};
+
+use anyhow::{anyhow, Result};
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+// a file of the program, `file` is `None` for a single main file
+struct Unit<'a> {
+    path:  &'a PathBuf,
+    file:  Option<String>,
+    scope: Option<String>,
+}
+
+// assemble `files` into `out` in the format, returns the number of words and warnings
+pub fn assemble<W: Write>(
+    files: &[PathBuf],
+    options: &Options,
+    format: Format,
+    out: W,
+) -> Result<(usize, Vec<Diagnostic>)> {
+    if format != Format::Text && format != Format::Binary {
+        return Err(anyhow!("streaming supports text and bin formats only"));
+    }
+
+    let linked = files.len() > 1;
+    let units: Vec<Unit> = files
+        .iter()
+        .map(|path| Unit {
+            path,
+            file: linked.then(|| path.display().to_string()),
+            scope: path
+                .file_stem()
+                .filter(|_| linked)
+                .map(|stem| stem.to_string_lossy().to_string()),
+        })
+        .collect();
+
+    let mut symbols = Symbols::new();
+    let mut diagnostics = Diagnostics::default();
+
+    // pass 1
+    let mut labels = Labels::default();
+    let mut addr = 0;
+    for unit in units.iter() {
+        each_line(unit, |source, text| {
+            if !source.code.starts_with('(') {
+                addr += 1;
+                return;
+            }
+
+            match l_command::parse(addr, source)
+                .map(|l| parser::localize(Node::L(l), unit.scope.as_deref()))
+            {
+                Ok(Node::L(l)) => {
+                    diagnostics
+                        .diagnostics
+                        .extend(labels.define(&l.symbol.name, l.source()));
+                    symbols.add(l.symbol);
+                }
+                Ok(_) => {}
+                Err(err) => diagnostics.errors.push(located(err, text)),
+            }
+        })?;
+    }
+
+    // pass 2
+    let mut out = BufWriter::new(out);
+    let mut addr = 0;
+    let mut overflow = None; // the first instruction out of ROM
+    let mut ram_overflowed = false; // the first variable over the limit, the rest follow it
+    for unit in units.iter() {
+        let mut result = Ok(());
+        each_line(unit, |source, text| {
+            if source.code.starts_with('(') || result.is_err() {
+                return;
+            }
+
+            let node = if source.code.starts_with('.') {
+                let directive = text.split_whitespace().next().unwrap_or("");
+                Err(
+                    AsmError::new(ErrorKind::UnknownDirective, &source, 0, directive)
+                        .note("directives are not supported in streaming"),
+                )
+            } else if source.code.starts_with('@') {
+                a_command::parse(addr, source).map(Node::A)
+            } else {
+                c_command::parse_with_dialect(addr, source, options.dialect).map(Node::C)
+            };
+
+            let node = match node {
+                Ok(node) => parser::localize(node, unit.scope.as_deref()),
+                Err(err) => {
+                    diagnostics.errors.push(located(err, text));
+                    return;
+                }
+            };
+            addr += 1;
+            if addr == ROM_SIZE + 1 {
+                overflow = Some(node_source(&node).clone());
+            }
+
+            let node = match node {
+                Node::A(a) => {
+                    labels.reference(&a);
+                    a.symbols().into_iter().for_each(|name| {
+                        let defined = symbols.get(name.to_string()).is_some();
+                        let var = symbols.get_or_assign(name);
+                        if !defined && var.addr >= options.ram_limit && !ram_overflowed {
+                            ram_overflowed = true;
+                            diagnostics.diagnostics.push(Diagnostic::error(
+                                a.source(),
+                                ram_overflow(name, var.addr, options.ram_limit),
+                            ));
+                        }
+                    });
+
+                    let a = a.assign(&symbols);
+                    if let Some(diagnostic) = validator::check_value(&a, &symbols) {
+                        diagnostics.diagnostics.push(diagnostic);
+                        return;
+                    }
+                    Node::A(a)
+                }
+                _ => node,
+            };
+
+            // nothing is written after the first error
+            if diagnostics.errors.is_empty() && !has_error(&diagnostics.diagnostics) {
+                if let Some(word) = codegen::encode(&node) {
+                    result = write_word(&mut out, word, format);
+                }
+            }
+        })?;
+        result?;
+    }
+    out.flush()?;
+
+    if let Some(source) = overflow {
+        diagnostics
+            .diagnostics
+            .push(Diagnostic::error(&source, rom_overflow(addr)));
+    }
+
+    diagnostics.diagnostics.extend(labels.unreferenced());
+    diagnostics
+        .diagnostics
+        .sort_by_key(|d| (d.source.file.clone(), d.source.line));
+
+    if diagnostics.errors.is_empty() && !has_error(&diagnostics.diagnostics) {
+        Ok((addr, diagnostics.diagnostics))
+    } else {
+        Err(diagnostics.into())
+    }
+}
+
+// call `f` with the code without whitespaces and comments, and the original line
+fn each_line<F: FnMut(Source, &str)>(unit: &Unit, mut f: F) -> Result<()> {
+    let reader = BufReader::new(File::open(unit.path)?);
+    for (n, text) in reader.lines().enumerate() {
+        let text = text?;
+        let (code, _comment) = parser::split_code(&text, "//", false);
+        let code = parser::strip_whitespaces(code.unwrap_or(""));
+        if code.is_empty() {
+            continue;
+        }
+
+        let source = Source {
+            file: unit.file.clone(),
+            line: n + 1,
+            code,
+        };
+        f(source, &text);
+    }
+    Ok(())
+}
+
+fn has_error(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.is_error())
+}
+
+fn node_source(node: &Node) -> &Source {
+    match node {
+        Node::A(a) => a.source(),
+        Node::C(c) => c.source(),
+        Node::L(l) => l.source(),
+    }
+}
+
+fn located(mut err: AsmError, text: &str) -> AsmError {
+    err.locate(text);
+    err
+}
+
+fn write_word<W: Write>(out: &mut W, word: u16, format: Format) -> Result<()> {
+    match format {
+        Format::Binary => out.write_all(&word.to_be_bytes())?,
+        _ => writeln!(out, "{:016b}", word)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+// symbol file (*.sym)
+//
+//   (LOOP) 4    label and its ROM address
+//   i 16        variable and its RAM address
+//   4 12 @i     ROM address, line number and code of the source
+//   5 lib.asm:3 D=M   source in an included file
+use super::parser::{Node, Source};
+use super::symbols::{SymbolKind, Symbols};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct SymbolMap {
+    pub labels:    BTreeMap<String, usize>,
+    pub variables: BTreeMap<String, usize>,
+    pub sources:   BTreeMap<usize, Source>, // ROM address => source
+}
+
+impl SymbolMap {
+    pub fn new() -> SymbolMap {
+        SymbolMap::default()
+    }
+
+    // collect labels and variables from resolved symbols, and sources from nodes
+    pub fn build(symbols: &Symbols, nodes: &[Node]) -> SymbolMap {
+        let collect = |kind| {
+            symbols
+                .symbols(kind)
+                .into_iter()
+                .map(|sym| (sym.name.clone(), sym.addr))
+                .collect()
+        };
+
+        let sources = nodes
+            .iter()
+            .filter_map(|node| match node {
+                Node::A(a) => Some((a.addr, a.source().clone())),
+                Node::C(c) => Some((c.addr, c.source().clone())),
+                Node::L(_) => None,
+            })
+            .collect();
+
+        SymbolMap {
+            labels: collect(SymbolKind::Label),
+            variables: collect(SymbolKind::Variable),
+            sources,
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<SymbolMap> {
+        let mut map = SymbolMap::new();
+
+        for (line, content) in contents.lines().enumerate() {
+            let content = content.split("//").next().unwrap_or("").trim();
+            if content.is_empty() {
+                continue;
+            }
+
+            let mut fields = content.splitn(3, char::is_whitespace);
+            let (name, addr, code) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(name), Some(addr), code) => (name, addr.trim(), code),
+                _ => {
+                    return Err(anyhow!(
+                        "line {} : expected `NAME ADDR` : {}",
+                        line + 1,
+                        content
+                    ))
+                }
+            };
+            let parse_addr = |addr: &str| {
+                addr.parse::<usize>().map_err(|err| {
+                    anyhow!("line {} : invalid address {} : {}", line + 1, addr, err)
+                })
+            };
+
+            match code {
+                // `ROM [FILE:]LINE CODE`
+                Some(code) => {
+                    let (file, lineno) = match addr.rsplit_once(':') {
+                        Some((file, lineno)) => (Some(file.to_string()), lineno),
+                        None => (None, addr),
+                    };
+                    let source = Source {
+                        file,
+                        line: parse_addr(lineno)?,
+                        code: code.trim().to_string(),
+                    };
+                    map.sources.insert(parse_addr(name)?, source);
+                }
+                None if name.starts_with('(') && name.ends_with(')') && name.len() > 2 => {
+                    map.labels
+                        .insert(name[1..name.len() - 1].to_string(), parse_addr(addr)?);
+                }
+                None => {
+                    map.variables.insert(name.to_string(), parse_addr(addr)?);
+                }
+            }
+        }
+
+        Ok(map)
+    }
+
+    pub fn to_sym(&self) -> String {
+        let mut lines = vec![String::from("// labels")];
+        lines.extend(
+            self.labels
+                .iter()
+                .map(|(name, addr)| format!("({}) {}", name, addr)),
+        );
+
+        lines.push(String::from("// variables"));
+        lines.extend(
+            self.variables
+                .iter()
+                .map(|(name, addr)| format!("{} {}", name, addr)),
+        );
+
+        lines.push(String::from("// sources"));
+        lines.extend(
+            self.sources
+                .iter()
+                .map(|(addr, source)| match &source.file {
+                    Some(file) => format!("{} {}:{} {}", addr, file, source.line, source.code),
+                    None => format!("{} {} {}", addr, source.line, source.code),
+                }),
+        );
+
+        lines.join("\n") + "\n"
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|err| anyhow!("{}", err))
+    }
+}
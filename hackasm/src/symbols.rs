@@ -1,4 +1,3 @@
-use super::a_command::*;
 use super::parser::*;
 
 use std::collections::HashMap;
@@ -7,14 +6,19 @@ use std::collections::HashMap;
 pub struct Symbol {
     pub name: String,
     pub addr: usize,
+    pub kind: SymbolKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Predefined, // SP, R0-R15, SCREEN...
+    Label,      // (LABEL), ROM address
+    Variable,   // @foo, RAM address
 }
 
 impl Symbol {
-    pub fn new(name: String, addr: usize) -> Symbol {
-        Symbol {
-            name: name,
-            addr: addr,
-        }
+    pub fn new(name: String, addr: usize, kind: SymbolKind) -> Symbol {
+        Symbol { name, addr, kind }
     }
 }
 
@@ -33,38 +37,38 @@ const DEFINED_SYMBOLS: &[(&str, usize)] = &[
     ("KBD", 24576),
 ];
 
+// SP, LCL, ..., SCREEN, KBD and R0-R15
+pub fn is_predefined(name: &str) -> bool {
+    DEFINED_SYMBOLS.iter().any(|(defined, _)| *defined == name)
+        || (0..16).any(|n| name == format!("R{}", n))
+}
+
+impl Default for Symbols {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Symbols {
     pub fn new() -> Symbols {
         let mut table: HashMap<String, Symbol> = HashMap::new();
 
         // defined symbols
-        DEFINED_SYMBOLS.into_iter().for_each(|(name, addr)| {
+        DEFINED_SYMBOLS.iter().for_each(|(name, addr)| {
             let name = String::from(*name);
             table.insert(
                 name.clone(),
-                Symbol {
-                    name: name,
-                    addr: *addr,
-                },
+                Symbol::new(name, *addr, SymbolKind::Predefined),
             );
         });
 
         // R0-R15
         for n in 0..16 {
             let name = format!("R{}", n);
-            table.insert(
-                name.clone(),
-                Symbol {
-                    name: name,
-                    addr: n,
-                },
-            );
+            table.insert(name.clone(), Symbol::new(name, n, SymbolKind::Predefined));
         }
 
-        Symbols {
-            table:  table,
-            offset: 16,
-        }
+        Symbols { table, offset: 16 }
     }
 
     pub fn get_or_assign(&mut self, name: &str) -> &Symbol {
@@ -74,10 +78,7 @@ impl Symbols {
 
         self.table.entry(name.clone()).or_insert_with(|| {
             assigned = true;
-            Symbol {
-                name: name.clone(),
-                addr: addr,
-            }
+            Symbol::new(name.clone(), addr, SymbolKind::Variable)
         });
 
         if assigned {
@@ -96,6 +97,14 @@ impl Symbols {
         self.table.get(&name)
     }
 
+    // symbols of the given kind, sorted by address
+    pub fn symbols(&self, kind: SymbolKind) -> Vec<&Symbol> {
+        let mut symbols: Vec<&Symbol> =
+            self.table.values().filter(|sym| sym.kind == kind).collect();
+        symbols.sort_by(|a, b| (a.addr, &a.name).cmp(&(b.addr, &b.name)));
+        symbols
+    }
+
     pub fn resolve(&mut self, nodes: Vec<Node>) -> Vec<Node> {
         // collect symbols
         self.collect_symbols(&nodes);
@@ -104,22 +113,18 @@ impl Symbols {
         nodes
             .into_iter()
             .map(|node| match node {
-                Node::A(a) => Node::A(a.assign(&self)),
+                Node::A(a) => Node::A(a.assign(self)),
                 _ => node,
             })
             .collect()
     }
 
-    fn collect_symbols(&mut self, nodes: &Vec<Node>) {
-        nodes.into_iter().for_each(|node| {
-            match node {
-                Node::A(ACommand {
-                    symbol_name: Some(name),
-                    ..
-                }) => {
-                    self.get_or_assign(&name);
-                }
-                _ => {}
+    fn collect_symbols(&mut self, nodes: &[Node]) {
+        nodes.iter().for_each(|node| {
+            if let Node::A(a) = node {
+                a.symbols().into_iter().for_each(|name| {
+                    self.get_or_assign(name);
+                });
             };
         });
     }
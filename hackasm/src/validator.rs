@@ -0,0 +1,220 @@
+// validation pass over parsed nodes
+//
+//   duplicate (LABEL)s
+//   labels shadowing predefined symbols (R3, SCREEN...)
+//   constants outside 0..32767
+//   labels never referenced
+use super::a_command::ACommand;
+use super::parser::{Node, Source};
+use super::symbols::{self, Symbols};
+
+use serde::Serialize;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// max value of A-Command constant (15bit)
+pub const MAX_CONSTANT: i64 = 0x7fff;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub level:   Level,
+    pub source:  Source,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(source: &Source, message: String) -> Diagnostic {
+        Diagnostic {
+            level: Level::Error,
+            source: source.clone(),
+            message,
+        }
+    }
+
+    pub fn warning(source: &Source, message: String) -> Diagnostic {
+        Diagnostic {
+            level: Level::Warning,
+            source: source.clone(),
+            message,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == Level::Error
+    }
+
+    // `Prog.asm:12 : error : duplicate label LOOP, first defined at line 5 : (LOOP)`
+    pub fn render(&self, filename: &str) -> String {
+        format!(
+            "{}:{} : {} : {} : {}",
+            self.source.file.as_deref().unwrap_or(filename),
+            self.source.line,
+            self.level,
+            self.message,
+            self.source.code
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+// `12 : error : duplicate label LOOP, first defined at line 5`
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} : {} : {}",
+            self.source.line, self.level, self.message
+        )
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Error => write!(f, "error"),
+            Level::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+pub fn validate(nodes: &[Node]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut labels = Labels::default();
+
+    nodes.iter().for_each(|node| match node {
+        Node::L(l) => diagnostics.extend(labels.define(&l.symbol.name, l.source())),
+        Node::A(a) => {
+            labels.reference(a);
+
+            // expressions are checked after resolved
+            if let Some(message) = check_range(a) {
+                diagnostics.push(Diagnostic::error(a.source(), message));
+            }
+        }
+        Node::C(_) => {}
+    });
+    diagnostics.extend(labels.unreferenced());
+
+    diagnostics.sort_by_key(|d| (d.source.file.clone(), d.source.line));
+    diagnostics
+}
+
+// definitions and references of labels, shared with the streaming assembler
+#[derive(Debug, Default)]
+pub struct Labels {
+    definitions: HashMap<String, Source>, // label name => source of the first definition
+    references:  HashSet<String>,
+}
+
+impl Labels {
+    // labels shadowing predefined symbols and duplicate labels
+    pub fn define(&mut self, name: &str, source: &Source) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        if symbols::is_predefined(name) {
+            diagnostics.push(Diagnostic::error(
+                source,
+                format!("label {} shadows predefined symbol", name),
+            ));
+        }
+
+        match self.definitions.get(name) {
+            Some(first) => diagnostics.push(Diagnostic::error(
+                source,
+                match &first.file {
+                    Some(file) => format!(
+                        "duplicate label {}, first defined at {}:{}",
+                        name, file, first.line
+                    ),
+                    None => format!(
+                        "duplicate label {}, first defined at line {}",
+                        name, first.line
+                    ),
+                },
+            )),
+            None => {
+                self.definitions.insert(name.to_string(), source.clone());
+            }
+        }
+
+        diagnostics
+    }
+
+    pub fn reference(&mut self, a: &ACommand) {
+        self.references
+            .extend(a.symbols().into_iter().map(String::from));
+    }
+
+    // warnings of labels never referenced, in order of definition
+    pub fn unreferenced(&self) -> Vec<Diagnostic> {
+        let mut unreferenced: Vec<(&String, &Source)> = self
+            .definitions
+            .iter()
+            .filter(|(name, _)| !self.references.contains(*name))
+            .collect();
+        unreferenced.sort_by_key(|(_, source)| (source.file.clone(), source.line));
+
+        unreferenced
+            .into_iter()
+            .map(|(name, source)| {
+                Diagnostic::warning(source, format!("label {} is never referenced", name))
+            })
+            .collect()
+    }
+}
+
+// values of expressions after symbols are resolved
+pub fn validate_values(nodes: &[Node], symbols: &Symbols) -> Vec<Diagnostic> {
+    nodes
+        .iter()
+        .filter_map(|node| match node {
+            Node::A(a) => {
+                check_expr(a, symbols).map(|message| Diagnostic::error(a.source(), message))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// constants and expressions of a resolved A-Command
+pub fn check_value(a: &ACommand, symbols: &Symbols) -> Option<Diagnostic> {
+    check_range(a)
+        .or_else(|| check_expr(a, symbols))
+        .map(|message| Diagnostic::error(a.source(), message))
+}
+
+// constants must fit in 15bit
+fn check_range(a: &ACommand) -> Option<String> {
+    if a.symbol_name.is_some() || a.expr.is_some() || (0..=MAX_CONSTANT).contains(&a.value) {
+        return None;
+    }
+
+    Some(format!(
+        "constant {} is out of range 0..{}",
+        a.value, MAX_CONSTANT
+    ))
+}
+
+// expressions must not overflow and fit in 15bit
+fn check_expr(a: &ACommand, symbols: &Symbols) -> Option<String> {
+    let expr = a.expr.as_ref()?;
+
+    match a.eval(symbols) {
+        None => Some(format!("expression {} overflows", expr)),
+        Some(value) if !(0..=MAX_CONSTANT).contains(&value) => Some(format!(
+            "expression {} evaluates to {}, out of range 0..{}",
+            expr, value, MAX_CONSTANT
+        )),
+        Some(_) => None,
+    }
+}
@@ -0,0 +1,89 @@
+// errors and warnings of programs which must not be assembled silently
+use hackasm::output::Format;
+use hackasm::stream;
+use hackasm::{assemble, Options};
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn messages(contents: &str, options: &Options) -> Vec<String> {
+    let sources = vec![(PathBuf::from("Prog.asm"), contents.to_string())];
+    match assemble(&sources, options) {
+        Ok(program) => program.warnings.iter().map(|d| d.to_string()).collect(),
+        Err(diagnostics) => diagnostics.to_string().lines().map(String::from).collect(),
+    }
+}
+
+// same as `messages` with the streaming assembler
+fn stream_messages(contents: &str, options: &Options) -> Vec<String> {
+    // tests run in parallel
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNT.fetch_add(1, Ordering::SeqCst);
+    let file = std::env::temp_dir().join(format!("hackasm-{}-{}.asm", std::process::id(), n));
+    std::fs::write(&file, contents).unwrap();
+    let result = stream::assemble(
+        std::slice::from_ref(&file),
+        options,
+        Format::Text,
+        Vec::new(),
+    );
+    std::fs::remove_file(&file).unwrap();
+
+    match result {
+        Ok((_, warnings)) => warnings.iter().map(|d| d.to_string()).collect(),
+        Err(err) => err.to_string().lines().map(String::from).collect(),
+    }
+}
+
+#[test]
+fn expression_overflow() {
+    let options = Options::default();
+
+    let constant = messages("@9223372036854775807+1\nD=A\n", &options);
+    assert_eq!(constant.len(), 1);
+    assert!(
+        constant[0].contains("arithmetic overflow"),
+        "{:?}",
+        constant
+    );
+
+    let resolved = messages("@X+9223372036854775807\nD=A\n", &options);
+    assert_eq!(
+        resolved,
+        vec!["1 : error : expression X+9223372036854775807 overflows"]
+    );
+}
+
+// the streaming assembler reports the same first variable over the limit
+#[test]
+fn ram_limit() {
+    let contents = "@foo\nM=1\n@bar\nM=1\n";
+    let options = Options {
+        ram_limit: 10,
+        ..Options::default()
+    };
+    let expected = vec!["1 : error : variable foo is allocated at RAM[16], over the limit 10"];
+    assert_eq!(messages(contents, &options), expected);
+    assert_eq!(stream_messages(contents, &options), expected);
+}
+
+#[test]
+fn labels() {
+    let options = Options::default();
+
+    let contents = "(LOOP)\n@LOOP\n0;JMP\n(END)\n(LOOP)\n(SCREEN)\n@32768\n";
+    let expected = vec![
+        "4 : warning : label END is never referenced",
+        "5 : error : duplicate label LOOP, first defined at line 1",
+        "6 : error : label SCREEN shadows predefined symbol",
+        "6 : warning : label SCREEN is never referenced",
+        "7 : error : constant 32768 is out of range 0..32767",
+    ];
+    assert_eq!(messages(contents, &options), expected);
+    assert_eq!(stream_messages(contents, &options), expected);
+
+    let contents = "(LOOP)\n@LOOP\n0;JMP\n(END)\n";
+    let expected = vec!["4 : warning : label END is never referenced"];
+    assert_eq!(messages(contents, &options), expected);
+    assert_eq!(stream_messages(contents, &options), expected);
+}
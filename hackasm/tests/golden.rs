@@ -0,0 +1,135 @@
+// assemble every *.asm under 06/ and compare with the expected *.ok.hack,
+// `PongL.asm` (without symbols) shares the fixture `Pong.ok.hack`
+use hackasm::output::Format;
+use hackasm::stream;
+use hackasm::{assemble, Options};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn programs() -> Vec<PathBuf> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../06");
+    let mut files: Vec<PathBuf> = fs::read_dir(&root)
+        .expect("06 directory")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|dir| dir.is_dir())
+        .flat_map(|dir| fs::read_dir(dir).expect("program directory"))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "asm"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn fixture(asm: &Path) -> PathBuf {
+    let stem = asm.file_stem().unwrap().to_string_lossy();
+    let name = stem.strip_suffix('L').unwrap_or(&stem);
+    asm.with_file_name(format!("{}.ok.hack", name))
+}
+
+#[test]
+fn assemble_06_programs() {
+    let files = programs();
+    assert!(!files.is_empty());
+
+    files.iter().for_each(|file| {
+        let contents = fs::read_to_string(file).unwrap();
+        let expected = fs::read_to_string(fixture(file))
+            .unwrap_or_else(|err| panic!("fixture of {} : {}", file.display(), err));
+
+        let program = assemble(&[(file.clone(), contents)], &Options::default())
+            .unwrap_or_else(|err| panic!("{} : {}", file.display(), err));
+
+        assert_eq!(
+            program.to_hack(),
+            expected,
+            "{} differs from the fixture",
+            file.display()
+        );
+    });
+}
+
+#[test]
+fn optimized_06_programs_are_not_longer() {
+    programs().iter().for_each(|file| {
+        let contents = fs::read_to_string(file).unwrap();
+        let sources = [(file.clone(), contents)];
+
+        let plain = assemble(&sources, &Options::default()).unwrap();
+        let options = Options {
+            optimize: true,
+            ..Options::default()
+        };
+        let optimized = assemble(&sources, &options).unwrap();
+
+        assert!(optimized.words.len() <= plain.words.len());
+    });
+}
+
+#[test]
+fn stream_06_programs() {
+    programs().iter().for_each(|file| {
+        let expected = fs::read_to_string(fixture(file)).unwrap();
+
+        let mut out = Vec::new();
+        stream::assemble(
+            std::slice::from_ref(file),
+            &Options::default(),
+            Format::Text,
+            &mut out,
+        )
+        .unwrap_or_else(|err| panic!("{} : {}", file.display(), err));
+
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    });
+}
+
+// instructions between a label and its offset are kept by the optimizer
+#[test]
+fn optimized_label_offsets() {
+    let contents = "@SP\nM=M+1\n@SP\nAM=M-1\n@TABLE+6\nD=A\n(TABLE)\n@SP\nM=M+1\n@SP\nAM=M-1\n@0\nD=A\n@R1\nM=D\n(END)\n@END\n0;JMP\n";
+    let sources = [(PathBuf::from("Table.asm"), contents.to_string())];
+
+    let plain = assemble(&sources, &Options::default()).unwrap();
+    let options = Options {
+        optimize: true,
+        ..Options::default()
+    };
+    let optimized = assemble(&sources, &options).unwrap();
+    assert!(optimized.words.len() < plain.words.len());
+
+    // `@TABLE+6` loads the address of `@R1` at line 14
+    [plain, optimized].iter().for_each(|program| {
+        let target = program
+            .words
+            .iter()
+            .find(|word| **word & 0x8000 == 0 && **word != 0)
+            .copied()
+            .unwrap();
+        assert_eq!(program.map.sources[&(target as usize)].line, 14);
+    });
+}
+
+// whitespace is dropped outside character literals only
+#[test]
+fn space_character_literal() {
+    let contents = "@' ' // space\nD=A\n@ 'a'\n";
+    let expected = "0000000000100000\n1110110000010000\n0000000001100001\n";
+
+    let sources = [(PathBuf::from("Space.asm"), contents.to_string())];
+    let program = assemble(&sources, &Options::default()).unwrap();
+    assert_eq!(program.to_hack(), expected);
+
+    let file = std::env::temp_dir().join(format!("hackasm-space-{}.asm", std::process::id()));
+    fs::write(&file, contents).unwrap();
+    let mut out = Vec::new();
+    let result = stream::assemble(
+        std::slice::from_ref(&file),
+        &Options::default(),
+        Format::Text,
+        &mut out,
+    );
+    fs::remove_file(&file).unwrap();
+    result.unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
@@ -0,0 +1,40 @@
+use hackasm::c_command::Dialect;
+use hackasm::repl::Repl;
+
+#[test]
+fn ram_dump_is_clipped() {
+    let mut repl = Repl::new(Dialect::Strict);
+
+    let dump = repl.eval(":ram 32766 18446744073709551615").unwrap();
+    assert_eq!(dump, "RAM[32766]=0\nRAM[32767]=0");
+
+    let dump = repl.eval(":ram 1 2").unwrap();
+    assert_eq!(dump, "RAM[1]=0\nRAM[2]=0");
+}
+
+#[test]
+fn out_of_range_constants_are_rejected() {
+    let mut repl = Repl::new(Dialect::Strict);
+    repl.eval(":exec");
+
+    ["@-1", "@40000"].iter().for_each(|input| {
+        let result = repl.eval(input).unwrap();
+        assert!(
+            result.starts_with("error: constant"),
+            "{} : {}",
+            input,
+            result
+        );
+        assert!(
+            result.ends_with("is out of range 0..32767"),
+            "{} : {}",
+            input,
+            result
+        );
+    });
+
+    // rejected instructions take no ROM address and leave A unchanged
+    let result = repl.eval("@32767").unwrap();
+    assert!(result.starts_with("0000  0111111111111111"), "{}", result);
+    assert!(result.ends_with("A=32767"), "{}", result);
+}
@@ -0,0 +1,111 @@
+// random instruction sequences are assembled, disassembled and assembled again,
+// the machine codes must be the same
+use hackasm::c_command::{Dialect, COMP_MAP, SHIFT_MAP};
+use hackasm::codegen;
+use hackasm::disassembler;
+use hackasm::parser;
+use hackasm::symbol_map::SymbolMap;
+use hackasm::symbols::Symbols;
+use hackasm::{assemble, Options};
+
+use proptest::prelude::*;
+
+use std::path::{Path, PathBuf};
+
+const DESTS: [&str; 8] = ["", "M", "D", "MD", "A", "AM", "AD", "AMD"];
+const JUMPS: [&str; 8] = ["", "JGT", "JEQ", "JGE", "JLT", "JNE", "JLE", "JMP"];
+
+// extended spellings of the same instructions
+const EXTENDED_DESTS: [&str; 6] = ["DM", "MA", "DA", "MDA", "DAM", "ADM"];
+const EXTENDED_COMPS: [&str; 8] = ["A+D", "1+M", "A&D", "M|D", "D<<", "A>>", "M<<", "1+D"];
+
+fn comps(extended: bool) -> Vec<&'static str> {
+    let mut comps: Vec<&str> = COMP_MAP.keys().copied().collect();
+    if extended {
+        comps.extend(SHIFT_MAP.keys());
+        comps.extend(EXTENDED_COMPS.iter());
+    }
+    comps.sort();
+    comps
+}
+
+fn c_command(dest: &str, comp: &str, jump: &str) -> String {
+    let mut code = String::new();
+    if !dest.is_empty() {
+        code.push_str(dest);
+        code.push('=');
+    }
+    code.push_str(comp);
+    if !jump.is_empty() {
+        code.push(';');
+        code.push_str(jump);
+    }
+    code
+}
+
+fn instruction(extended: bool) -> impl Strategy<Value = String> {
+    let mut dests: Vec<&str> = DESTS.to_vec();
+    if extended {
+        dests.extend(EXTENDED_DESTS.iter());
+    }
+
+    prop_oneof![
+        (0..=0x7fff_i64).prop_map(|value| format!("@{}", value)),
+        (
+            prop::sample::select(dests),
+            prop::sample::select(comps(extended)),
+            prop::sample::select(JUMPS.to_vec()),
+        )
+            .prop_map(|(dest, comp, jump)| c_command(dest, comp, jump)),
+    ]
+}
+
+fn program(extended: bool) -> impl Strategy<Value = String> {
+    prop::collection::vec(instruction(extended), 1..64).prop_map(|lines| lines.join("\n") + "\n")
+}
+
+// *.hack through `gen_a` and `gen_c`
+fn generate(asm: &str, dialect: Dialect) -> String {
+    let mut symbols = Symbols::new();
+    let (nodes, errors) = parser::parse_with_dir(asm, Path::new("."), dialect, &mut symbols);
+    assert!(errors.is_empty(), "{:?}", errors);
+    codegen::generate(symbols.resolve(nodes))
+}
+
+fn roundtrip(asm: &str, dialect: Dialect) -> Result<(), TestCaseError> {
+    let hack = generate(asm, dialect);
+
+    let options = Options {
+        dialect,
+        ..Options::default()
+    };
+    let program = assemble(&[(PathBuf::from("Prog.asm"), asm.to_string())], &options).unwrap();
+    prop_assert_eq!(&program.to_hack(), &hack);
+
+    let (disassembled, errors) = disassembler::disassemble(&hack, &SymbolMap::new());
+    prop_assert!(errors.is_empty(), "{:?}", errors);
+
+    prop_assert_eq!(generate(&disassembled, dialect), hack);
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn strict_roundtrip(asm in program(false)) {
+        roundtrip(&asm, Dialect::Strict)?;
+    }
+
+    #[test]
+    fn extended_roundtrip(asm in program(true)) {
+        roundtrip(&asm, Dialect::Extended)?;
+    }
+
+    #[test]
+    fn decode_every_instruction(word in 0..=0xffff_u16) {
+        // decoded instructions encode to the same word
+        if let Ok(code) = disassembler::decode(word) {
+            let hack = generate(&code, Dialect::Extended);
+            prop_assert_eq!(hack, format!("{:016b}\n", word));
+        }
+    }
+}
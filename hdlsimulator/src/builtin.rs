@@ -0,0 +1,350 @@
+// builtin chips, implemented in rust instead of HDL
+use super::parser::{Body, ChipDef, PinDecl};
+
+use anyhow::{anyhow, Result};
+use maplit::hashmap;
+
+use std::collections::HashMap;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gate {
+    // combinational chips
+    Nand,
+    Not,
+    And,
+    Or,
+    Xor,
+    Mux,
+    DMux,
+    Not16,
+    And16,
+    Or16,
+    Mux16,
+    Or8Way,
+    Mux4Way16,
+    Mux8Way16,
+    DMux4Way,
+    DMux8Way,
+    HalfAdder,
+    FullAdder,
+    Add16,
+    Inc16,
+    ALU,
+    // clocked chips
+    DFF,
+    Bit,
+    Register,
+    ARegister,
+    DRegister,
+    PC,
+    RAM8,
+    RAM64,
+    RAM512,
+    RAM4K,
+    RAM16K,
+    ROM32K,
+    Screen,
+    Keyboard,
+}
+
+// pin names and widths of a builtin chip
+pub struct Signature {
+    pub gate:    Gate,
+    pub inputs:  &'static [(&'static str, usize)],
+    pub outputs: &'static [(&'static str, usize)],
+}
+
+const OUT: &[(&str, usize)] = &[("out", 1)];
+const OUT16: &[(&str, usize)] = &[("out", 16)];
+const AB: &[(&str, usize)] = &[("a", 1), ("b", 1)];
+const AB16: &[(&str, usize)] = &[("a", 16), ("b", 16)];
+const ABCD: &[(&str, usize)] = &[("a", 1), ("b", 1), ("c", 1), ("d", 1)];
+const ABCDEFGH: &[(&str, usize)] = &[
+    ("a", 1),
+    ("b", 1),
+    ("c", 1),
+    ("d", 1),
+    ("e", 1),
+    ("f", 1),
+    ("g", 1),
+    ("h", 1),
+];
+const SUM: &[(&str, usize)] = &[("sum", 1), ("carry", 1)];
+const REGISTER: &[(&str, usize)] = &[("in", 16), ("load", 1)];
+
+macro_rules! signature {
+    ($gate:ident, $inputs:expr, $outputs:expr) => {
+        Signature {
+            gate:    Gate::$gate,
+            inputs:  $inputs,
+            outputs: $outputs,
+        }
+    };
+}
+
+lazy_static! {
+    pub static ref BUILTINS: HashMap<&'static str, Signature> = hashmap!(
+        "Nand"      => signature!(Nand, AB, OUT),
+        "Not"       => signature!(Not, &[("in", 1)], OUT),
+        "And"       => signature!(And, AB, OUT),
+        "Or"        => signature!(Or, AB, OUT),
+        "Xor"       => signature!(Xor, AB, OUT),
+        "Mux"       => signature!(Mux, &[("a", 1), ("b", 1), ("sel", 1)], OUT),
+        "DMux"      => signature!(DMux, &[("in", 1), ("sel", 1)], AB),
+        "Not16"     => signature!(Not16, &[("in", 16)], OUT16),
+        "And16"     => signature!(And16, AB16, OUT16),
+        "Or16"      => signature!(Or16, AB16, OUT16),
+        "Mux16"     => signature!(Mux16, &[("a", 16), ("b", 16), ("sel", 1)], OUT16),
+        "Or8Way"    => signature!(Or8Way, &[("in", 8)], OUT),
+        "Mux4Way16" => signature!(Mux4Way16, &[("a", 16), ("b", 16), ("c", 16), ("d", 16), ("sel", 2)], OUT16),
+        "Mux8Way16" => signature!(Mux8Way16, &[
+            ("a", 16), ("b", 16), ("c", 16), ("d", 16), ("e", 16), ("f", 16), ("g", 16), ("h", 16), ("sel", 3),
+        ], OUT16),
+        "DMux4Way"  => signature!(DMux4Way, &[("in", 1), ("sel", 2)], ABCD),
+        "DMux8Way"  => signature!(DMux8Way, &[("in", 1), ("sel", 3)], ABCDEFGH),
+        "HalfAdder" => signature!(HalfAdder, AB, SUM),
+        "FullAdder" => signature!(FullAdder, &[("a", 1), ("b", 1), ("c", 1)], SUM),
+        "Add16"     => signature!(Add16, AB16, OUT16),
+        "Inc16"     => signature!(Inc16, &[("in", 16)], OUT16),
+        "ALU"       => signature!(ALU, &[
+            ("x", 16), ("y", 16), ("zx", 1), ("nx", 1), ("zy", 1), ("ny", 1), ("f", 1), ("no", 1),
+        ], &[("out", 16), ("zr", 1), ("ng", 1)]),
+        "DFF"       => signature!(DFF, &[("in", 1)], OUT),
+        "Bit"       => signature!(Bit, &[("in", 1), ("load", 1)], OUT),
+        "Register"  => signature!(Register, REGISTER, OUT16),
+        "ARegister" => signature!(ARegister, REGISTER, OUT16),
+        "DRegister" => signature!(DRegister, REGISTER, OUT16),
+        "PC"        => signature!(PC, &[("in", 16), ("load", 1), ("inc", 1), ("reset", 1)], OUT16),
+        "RAM8"      => signature!(RAM8, &[("in", 16), ("load", 1), ("address", 3)], OUT16),
+        "RAM64"     => signature!(RAM64, &[("in", 16), ("load", 1), ("address", 6)], OUT16),
+        "RAM512"    => signature!(RAM512, &[("in", 16), ("load", 1), ("address", 9)], OUT16),
+        "RAM4K"     => signature!(RAM4K, &[("in", 16), ("load", 1), ("address", 12)], OUT16),
+        "RAM16K"    => signature!(RAM16K, &[("in", 16), ("load", 1), ("address", 14)], OUT16),
+        "ROM32K"    => signature!(ROM32K, &[("address", 15)], OUT16),
+        "Screen"    => signature!(Screen, &[("in", 16), ("load", 1), ("address", 13)], OUT16),
+        "Keyboard"  => signature!(Keyboard, &[], OUT16),
+    );
+}
+
+// chip definition of the builtin chip `name`
+pub fn chip_def(name: &str) -> Option<ChipDef> {
+    let sig = BUILTINS.get(name)?;
+    let decls = |pins: &[(&str, usize)]| {
+        pins.iter()
+            .map(|(name, width)| PinDecl {
+                name:  name.to_string(),
+                width: *width,
+            })
+            .collect()
+    };
+
+    Some(ChipDef {
+        name:    name.to_string(),
+        source:  format!("builtin {}", name),
+        inputs:  decls(sig.inputs),
+        outputs: decls(sig.outputs),
+        body:    Body::Builtin(name.to_string()),
+    })
+}
+
+// an instance of the builtin chip with its registers or memory
+#[derive(Debug, Clone)]
+pub struct Builtin {
+    pub gate: Gate,
+    memory:   Vec<u16>,
+    latched:  Option<(usize, u16)>, // (address, value) to be written at tock
+}
+
+impl Builtin {
+    pub fn new(gate: Gate) -> Builtin {
+        let size = match gate {
+            Gate::DFF
+            | Gate::Bit
+            | Gate::Register
+            | Gate::ARegister
+            | Gate::DRegister
+            | Gate::PC => 1,
+            Gate::Keyboard => 1,
+            Gate::RAM8 => 8,
+            Gate::RAM64 => 64,
+            Gate::RAM512 => 512,
+            Gate::RAM4K => 4096,
+            Gate::RAM16K => 16384,
+            Gate::ROM32K => 32768,
+            Gate::Screen => 8192,
+            _ => 0,
+        };
+
+        Builtin {
+            gate,
+            memory: vec![0; size],
+            latched: None,
+        }
+    }
+
+    // true if the input pin affects the outputs without waiting for the clock
+    pub fn is_combinational_input(&self, index: usize) -> bool {
+        match self.gate {
+            Gate::DFF
+            | Gate::Bit
+            | Gate::Register
+            | Gate::ARegister
+            | Gate::DRegister
+            | Gate::PC => false,
+            Gate::Keyboard => false,
+            // out = memory[address]
+            Gate::RAM8 | Gate::RAM64 | Gate::RAM512 | Gate::RAM4K | Gate::RAM16K | Gate::Screen => {
+                index == 2
+            }
+            _ => true,
+        }
+    }
+
+    pub fn eval(&self, inputs: &[u16]) -> Vec<u16> {
+        let pin = |i: usize| inputs[i];
+        let bit = |b: bool| b as u16;
+
+        match self.gate {
+            Gate::Nand => vec![bit(pin(0) & pin(1) == 0)],
+            Gate::Not => vec![bit(pin(0) == 0)],
+            Gate::And => vec![pin(0) & pin(1)],
+            Gate::Or => vec![pin(0) | pin(1)],
+            Gate::Xor => vec![pin(0) ^ pin(1)],
+            Gate::Mux | Gate::Mux16 => vec![if pin(2) == 0 { pin(0) } else { pin(1) }],
+            Gate::DMux => {
+                if pin(1) == 0 {
+                    vec![pin(0), 0]
+                } else {
+                    vec![0, pin(0)]
+                }
+            }
+            Gate::Not16 => vec![!pin(0)],
+            Gate::And16 => vec![pin(0) & pin(1)],
+            Gate::Or16 => vec![pin(0) | pin(1)],
+            Gate::Or8Way => vec![bit(pin(0) != 0)],
+            Gate::Mux4Way16 => vec![pin(pin(4) as usize)],
+            Gate::Mux8Way16 => vec![pin(pin(8) as usize)],
+            Gate::DMux4Way | Gate::DMux8Way => {
+                let ways = if self.gate == Gate::DMux4Way { 4 } else { 8 };
+                (0..ways)
+                    .map(|i| if i == pin(1) { pin(0) } else { 0 })
+                    .collect()
+            }
+            Gate::HalfAdder => vec![pin(0) ^ pin(1), pin(0) & pin(1)],
+            Gate::FullAdder => {
+                let sum = pin(0) + pin(1) + pin(2);
+                vec![sum & 1, sum >> 1]
+            }
+            Gate::Add16 => vec![pin(0).wrapping_add(pin(1))],
+            Gate::Inc16 => vec![pin(0).wrapping_add(1)],
+            Gate::ALU => {
+                let out = alu(pin(0), pin(1), &inputs[2..8]);
+                vec![out, bit(out == 0), out >> 15]
+            }
+            Gate::Keyboard => vec![self.memory[0]],
+            Gate::ROM32K => vec![self.memory[pin(0) as usize]],
+            Gate::RAM8 | Gate::RAM64 | Gate::RAM512 | Gate::RAM4K | Gate::RAM16K | Gate::Screen => {
+                vec![self.memory[pin(2) as usize]]
+            }
+            // registers
+            _ => vec![self.memory[0]],
+        }
+    }
+
+    // rising edge of the clock: latch inputs
+    pub fn tick(&mut self, inputs: &[u16]) {
+        let pin = |i: usize| inputs[i];
+
+        self.latched = match self.gate {
+            Gate::DFF => Some((0, pin(0))),
+            Gate::Bit | Gate::Register | Gate::ARegister | Gate::DRegister if pin(1) != 0 => {
+                Some((0, pin(0)))
+            }
+            Gate::PC => {
+                let out = self.memory[0];
+                let next = if pin(3) != 0 {
+                    0
+                } else if pin(1) != 0 {
+                    pin(0)
+                } else if pin(2) != 0 {
+                    out.wrapping_add(1)
+                } else {
+                    out
+                };
+                Some((0, next))
+            }
+            Gate::RAM8 | Gate::RAM64 | Gate::RAM512 | Gate::RAM4K | Gate::RAM16K | Gate::Screen
+                if pin(1) != 0 =>
+            {
+                Some((pin(2) as usize, pin(0)))
+            }
+            _ => None,
+        };
+    }
+
+    // falling edge of the clock: update outputs with latched values
+    pub fn tock(&mut self) {
+        if let Some((addr, value)) = self.latched.take() {
+            self.memory[addr] = value;
+        }
+    }
+
+    // register value or memory word, `Register[]` and `RAM16K[index]` in test scripts.
+    // the value latched at tick is visible before tock updates the outputs
+    pub fn get(&self, index: usize) -> Result<u16> {
+        if let Some((addr, value)) = self.latched {
+            if addr == index {
+                return Ok(value);
+            }
+        }
+
+        self.memory
+            .get(index)
+            .copied()
+            .ok_or_else(|| anyhow!("{:?}[{}] : index out of range", self.gate, index))
+    }
+
+    pub fn set(&mut self, index: usize, value: u16) -> Result<()> {
+        let gate = self.gate;
+        let word = self
+            .memory
+            .get_mut(index)
+            .ok_or_else(|| anyhow!("{:?}[{}] : index out of range", gate, index))?;
+        *word = value;
+        Ok(())
+    }
+
+    // fill memory from address 0 with `words`, e.g. `ROM32K load Prog.hack`
+    pub fn load(&mut self, words: &[u16]) -> Result<()> {
+        if words.len() > self.memory.len() {
+            return Err(anyhow!(
+                "{:?} : {} words do not fit in {} words",
+                self.gate,
+                words.len(),
+                self.memory.len()
+            ));
+        }
+
+        self.memory.iter_mut().for_each(|word| *word = 0);
+        self.memory[..words.len()].copy_from_slice(words);
+        Ok(())
+    }
+}
+
+// control bits: zx, nx, zy, ny, f, no
+fn alu(x: u16, y: u16, control: &[u16]) -> u16 {
+    let flag = |i: usize| control[i] != 0;
+
+    let x = if flag(0) { 0 } else { x };
+    let x = if flag(1) { !x } else { x };
+    let y = if flag(2) { 0 } else { y };
+    let y = if flag(3) { !y } else { y };
+    let out = if flag(4) { x.wrapping_add(y) } else { x & y };
+
+    if flag(5) {
+        !out
+    } else {
+        out
+    }
+}
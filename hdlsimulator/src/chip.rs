@@ -0,0 +1,343 @@
+// resolve chip definitions and flatten them into a netlist of builtin chips
+use super::builtin::{self, Builtin, BUILTINS};
+use super::parser::{parse, Body, ChipDef, Part, PinRef, Signal};
+
+use anyhow::{anyhow, Result};
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+// wire ids of constant `false` and `true`
+pub const FALSE: usize = 0;
+pub const TRUE: usize = 1;
+
+// wire ids of each bit of the pins, least significant bit first
+pub type Pins = HashMap<String, Vec<usize>>;
+
+// chips defined by `Name.hdl` in the directory, falling back to builtin chips
+pub struct Library {
+    dir:   PathBuf,
+    cache: HashMap<String, Rc<ChipDef>>,
+}
+
+impl Library {
+    pub fn new(dir: PathBuf) -> Library {
+        Library {
+            dir,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn resolve(&mut self, name: &str) -> Result<Rc<ChipDef>> {
+        if let Some(def) = self.cache.get(name) {
+            return Ok(def.clone());
+        }
+
+        let path = self.dir.join(format!("{}.hdl", name));
+        let def = if path.exists() {
+            let contents =
+                fs::read_to_string(&path).map_err(|err| anyhow!("{} : {}", path.display(), err))?;
+            let def = parse(&contents, &path.display().to_string())?;
+            if def.name != name {
+                return Err(anyhow!(
+                    "{} : defines chip {}, expected {}",
+                    path.display(),
+                    def.name,
+                    name
+                ));
+            }
+            def
+        } else {
+            builtin::chip_def(name).ok_or_else(|| anyhow!("unknown chip : {}", name))?
+        };
+
+        let def = Rc::new(def);
+        self.cache.insert(name.to_string(), def.clone());
+        Ok(def)
+    }
+}
+
+// a builtin chip and the wires connected to its pins
+pub struct Instance {
+    pub builtin: Builtin,
+    pub inputs:  Vec<Vec<usize>>,
+    pub outputs: Vec<Vec<usize>>,
+}
+
+// builtin chip instances connected by wires, wires are merged with union-find
+pub struct Netlist {
+    parents:       Vec<usize>,
+    pub instances: Vec<Instance>,
+}
+
+impl Netlist {
+    pub fn new() -> Netlist {
+        Netlist {
+            parents:   vec![FALSE, TRUE],
+            instances: vec![],
+        }
+    }
+
+    pub fn new_wires(&mut self, width: usize) -> Vec<usize> {
+        let start = self.parents.len();
+        self.parents.extend(start..start + width);
+        (start..start + width).collect()
+    }
+
+    pub fn find(&mut self, wire: usize) -> usize {
+        let mut root = wire;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        let mut wire = wire;
+        while self.parents[wire] != root {
+            let next = self.parents[wire];
+            self.parents[wire] = root;
+            wire = next;
+        }
+        root
+    }
+
+    // constants stay roots, so that `find(w) == TRUE` tells w is tied to true
+    fn connect(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if b <= TRUE {
+            self.parents[a] = b;
+        } else {
+            self.parents[b] = a;
+        }
+    }
+
+    // instantiate chip `def` whose pins are connected to `pins`.
+    // returns all pins and internal pins of the chip
+    pub fn instantiate(
+        &mut self,
+        library: &mut Library,
+        def: &ChipDef,
+        pins: Pins,
+        stack: &mut Vec<String>,
+    ) -> Result<Pins> {
+        if stack.contains(&def.name) {
+            return Err(anyhow!(
+                "{} : recursive chip definition : {}",
+                def.source,
+                stack.join(" -> ")
+            ));
+        }
+
+        match &def.body {
+            Body::Builtin(name) => self.instantiate_builtin(def, name, pins),
+            Body::Parts(parts) => {
+                stack.push(def.name.clone());
+                let pins = self.instantiate_parts(library, def, parts, pins, stack)?;
+                stack.pop();
+                Ok(pins)
+            }
+        }
+    }
+
+    fn instantiate_builtin(&mut self, def: &ChipDef, name: &str, pins: Pins) -> Result<Pins> {
+        let sig = BUILTINS
+            .get(name)
+            .ok_or_else(|| anyhow!("{} : unknown builtin chip : {}", def.source, name))?;
+
+        let wires = |decls: &[(&str, usize)]| {
+            decls
+                .iter()
+                .map(|(pin, width)| match pins.get(*pin) {
+                    Some(wires) if wires.len() == *width => Ok(wires.clone()),
+                    _ => Err(anyhow!(
+                        "{} : pin {}[{}] does not match builtin {}",
+                        def.source,
+                        pin,
+                        width,
+                        name
+                    )),
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+
+        self.instances.push(Instance {
+            builtin: Builtin::new(sig.gate),
+            inputs:  wires(sig.inputs)?,
+            outputs: wires(sig.outputs)?,
+        });
+        Ok(pins)
+    }
+
+    fn instantiate_parts(
+        &mut self,
+        library: &mut Library,
+        def: &ChipDef,
+        parts: &[Part],
+        pins: Pins,
+        stack: &mut Vec<String>,
+    ) -> Result<Pins> {
+        let mut scope = pins;
+        let mut children = Vec::new();
+
+        // 1st pass: part outputs define internal pins
+        for part in parts {
+            let err =
+                |msg: String| anyhow!("{}:{} : {} : {}", def.source, part.line, part.name, msg);
+
+            let child = library
+                .resolve(&part.name)
+                .map_err(|e| err(e.to_string()))?;
+            let child_pins: Pins = child
+                .inputs
+                .iter()
+                .chain(child.outputs.iter())
+                .map(|decl| (decl.name.clone(), self.new_wires(decl.width)))
+                .collect();
+
+            for conn in &part.connections {
+                if child.output(&conn.pin.name).is_none() {
+                    continue;
+                }
+                let part_wires = slice(&child_pins, &conn.pin).map_err(err)?;
+
+                let signal = match &conn.signal {
+                    Signal::Pin(signal) => signal,
+                    _ => {
+                        return Err(err(format!(
+                            "output pin {} can not be connected to a constant",
+                            conn.pin.name
+                        )))
+                    }
+                };
+
+                if def.input(&signal.name).is_some() {
+                    return Err(err(format!(
+                        "output pin {} can not be connected to input pin {}",
+                        conn.pin.name, signal.name
+                    )));
+                }
+
+                if def.output(&signal.name).is_some() {
+                    let wires = slice(&scope, signal).map_err(err)?;
+                    check_width(&conn.pin, &part_wires, signal, &wires).map_err(err)?;
+                    wires
+                        .iter()
+                        .zip(part_wires.iter())
+                        .for_each(|(a, b)| self.connect(*a, *b));
+                } else if signal.range.is_some() {
+                    return Err(err(format!(
+                        "sub bus of an internal pin {} may not be used",
+                        signal.name
+                    )));
+                } else if scope.contains_key(&signal.name) {
+                    return Err(err(format!(
+                        "internal pin {} has multiple sources",
+                        signal.name
+                    )));
+                } else {
+                    scope.insert(signal.name.clone(), part_wires);
+                }
+            }
+
+            children.push((part, child, child_pins));
+        }
+
+        // 2nd pass: connect part inputs, unconnected inputs are false
+        for (part, child, child_pins) in children {
+            let err =
+                |msg: String| anyhow!("{}:{} : {} : {}", def.source, part.line, part.name, msg);
+            let mut connected = HashSet::new();
+
+            for conn in &part.connections {
+                if child.output(&conn.pin.name).is_some() {
+                    continue;
+                }
+                if child.input(&conn.pin.name).is_none() {
+                    return Err(err(format!("unknown pin {}", conn.pin.name)));
+                }
+                let part_wires = slice(&child_pins, &conn.pin).map_err(err)?;
+
+                let wires = match &conn.signal {
+                    Signal::True => vec![TRUE; part_wires.len()],
+                    Signal::False => vec![FALSE; part_wires.len()],
+                    Signal::Pin(signal) if def.output(&signal.name).is_some() => {
+                        return Err(err(format!(
+                            "output pin {} can not be used as an input",
+                            signal.name
+                        )));
+                    }
+                    Signal::Pin(signal) if !scope.contains_key(&signal.name) => {
+                        return Err(err(format!("undefined pin {}", signal.name)));
+                    }
+                    Signal::Pin(signal) => {
+                        let wires = slice(&scope, signal).map_err(err)?;
+                        check_width(&conn.pin, &part_wires, signal, &wires).map_err(err)?;
+                        wires
+                    }
+                };
+
+                for (a, b) in wires.iter().zip(part_wires.iter()) {
+                    self.connect(*a, *b);
+                    connected.insert(*b);
+                }
+            }
+
+            for decl in &child.inputs {
+                for wire in &child_pins[&decl.name] {
+                    if !connected.contains(wire) {
+                        self.connect(FALSE, *wire);
+                    }
+                }
+            }
+
+            self.instantiate(library, &child, child_pins, stack)?;
+        }
+
+        Ok(scope)
+    }
+}
+
+impl Default for Netlist {
+    fn default() -> Self {
+        Netlist::new()
+    }
+}
+
+// wires of `pin`, `pin[i]` or `pin[i..j]`
+fn slice(pins: &Pins, pin: &PinRef) -> std::result::Result<Vec<usize>, String> {
+    let wires = pins
+        .get(&pin.name)
+        .ok_or_else(|| format!("unknown pin {}", pin.name))?;
+    match pin.range {
+        None => Ok(wires.clone()),
+        Some((from, to)) if to < wires.len() => Ok(wires[from..=to].to_vec()),
+        Some((from, to)) => Err(format!(
+            "sub bus {}[{}..{}] out of range, width is {}",
+            pin.name,
+            from,
+            to,
+            wires.len()
+        )),
+    }
+}
+
+fn check_width(
+    pin: &PinRef,
+    pin_wires: &[usize],
+    signal: &PinRef,
+    wires: &[usize],
+) -> std::result::Result<(), String> {
+    if pin_wires.len() == wires.len() {
+        return Ok(());
+    }
+    Err(format!(
+        "width mismatch : {} is {} bits but {} is {} bits",
+        pin.name,
+        pin_wires.len(),
+        signal.name,
+        wires.len()
+    ))
+}
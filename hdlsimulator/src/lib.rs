@@ -0,0 +1,9 @@
+#[macro_use]
+extern crate lazy_static;
+
+pub mod builtin;
+pub mod chip;
+pub mod parser;
+pub mod simulator;
+
+pub use simulator::Simulator;
@@ -0,0 +1,116 @@
+extern crate hdlsimulator;
+
+use hdlsimulator::Simulator;
+
+use getopts::Options;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+struct Config {
+    target: String,
+    sets:   Vec<(String, u16)>,
+    ticks:  usize,
+}
+
+/**
+ * 1. Read Chip.hdl and resolve its parts in the same directory or builtin chips
+ * 2. flatten the parts to a netlist of builtin chips
+ * 3. set input pins, evaluate and run tick/tock
+ * 4. print pins
+ */
+fn main() {
+    let config = parse_args();
+
+    let mut sim = Simulator::load(Path::new(&config.target)).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(1);
+    });
+
+    for (pin, value) in &config.sets {
+        sim.set(pin, *value).unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(1);
+        });
+    }
+
+    sim.eval();
+    for _ in 0..config.ticks {
+        sim.tick();
+        sim.tock();
+    }
+
+    println!("time: {}", sim.time());
+    for decl in sim.inputs.iter().chain(sim.outputs.iter()) {
+        let value = sim.get(&decl.name).unwrap_or(0);
+        println!(
+            "{} = {:0width$b} ({})",
+            decl.name,
+            value,
+            value as i16,
+            width = decl.width
+        );
+    }
+}
+
+fn parse_args() -> Config {
+    let args: Vec<String> = env::args().collect();
+    let mut opts = Options::new();
+
+    opts.optmulti("s", "set", "set input pin before evaluation", "PIN=VALUE");
+    opts.optopt(
+        "t",
+        "ticks",
+        "number of clock cycles to run (default 0)",
+        "N",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            process::exit(1);
+        }
+    };
+
+    let ticks = matches.opt_str("ticks").map_or(0, |n| {
+        n.parse::<usize>().unwrap_or_else(|err| {
+            println!("invalid ticks: {}, {}", n, err);
+            process::exit(1);
+        })
+    });
+
+    let sets = matches
+        .opt_strs("set")
+        .iter()
+        .map(|s| parse_set(s))
+        .collect();
+
+    let target = if !matches.free.is_empty() {
+        matches.free[0].clone()
+    } else {
+        println!("{}", opts.usage("usage: hdlsimulator [options] Chip.hdl"));
+        process::exit(1);
+    };
+
+    Config {
+        target,
+        sets,
+        ticks,
+    }
+}
+
+fn parse_set(s: &str) -> (String, u16) {
+    let parsed = s.split_once('=').and_then(|(pin, value)| {
+        value
+            .parse::<i32>()
+            .ok()
+            .map(|value| (pin.to_string(), value as u16))
+    });
+
+    parsed.unwrap_or_else(|| {
+        println!("invalid --set, expected PIN=VALUE: {}", s);
+        process::exit(1);
+    })
+}
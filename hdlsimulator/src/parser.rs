@@ -0,0 +1,398 @@
+// parser for HDL chip definitions
+//
+//   CHIP Name {
+//       IN a[16], b;
+//       OUT out[16];
+//       PARTS:
+//       Part(pin=wire, pin[0..7]=bus[8..15], pin[0]=true);
+//   }
+use anyhow::{anyhow, Result};
+
+#[derive(Debug, Clone)]
+pub struct ChipDef {
+    pub name:    String,
+    pub source:  String, // file name, used in error messages
+    pub inputs:  Vec<PinDecl>,
+    pub outputs: Vec<PinDecl>,
+    pub body:    Body,
+}
+
+#[derive(Debug, Clone)]
+pub enum Body {
+    Parts(Vec<Part>),
+    Builtin(String), // `BUILTIN Name;`
+}
+
+#[derive(Debug, Clone)]
+pub struct PinDecl {
+    pub name:  String,
+    pub width: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Part {
+    pub name:        String,
+    pub connections: Vec<Connection>,
+    pub line:        usize,
+}
+
+// `pin=signal` in a part
+#[derive(Debug, Clone)]
+pub struct Connection {
+    pub pin:    PinRef,
+    pub signal: Signal,
+}
+
+// `name`, `name[i]` or `name[i..j]`
+#[derive(Debug, Clone)]
+pub struct PinRef {
+    pub name:  String,
+    pub range: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Signal {
+    Pin(PinRef),
+    True,
+    False,
+}
+
+impl ChipDef {
+    pub fn input(&self, name: &str) -> Option<&PinDecl> {
+        self.inputs.iter().find(|pin| pin.name == name)
+    }
+
+    pub fn output(&self, name: &str) -> Option<&PinDecl> {
+        self.outputs.iter().find(|pin| pin.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(usize),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 11] = ["..", "{", "}", "(", ")", "[", "]", ";", ",", "=", ":"];
+
+pub fn parse(contents: &str, source: &str) -> Result<ChipDef> {
+    let tokens = tokenize(contents, source)?;
+    Parser {
+        tokens,
+        pos: 0,
+        source,
+    }
+    .chip()
+}
+
+// split into tokens with line number, dropping `//`, `/* */` and `/** */` comments
+fn tokenize(contents: &str, source: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = contents.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if rest == "//" {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if rest == "/*" {
+            let start = line;
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(anyhow!("{}:{} : unterminated comment", source, start));
+            }
+            i += 2;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let ident: String = chars[i..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_' || **c == '.')
+                .collect();
+            i += ident.len();
+            tokens.push((Token::Ident(ident), line));
+        } else if c.is_ascii_digit() {
+            let digits: String = chars[i..]
+                .iter()
+                .take_while(|c| c.is_ascii_digit())
+                .collect();
+            i += digits.len();
+            let n = digits.parse::<usize>().map_err(|err| {
+                anyhow!("{}:{} : invalid number {} : {}", source, line, digits, err)
+            })?;
+            tokens.push((Token::Number(n), line));
+        } else if let Some(sym) = SYMBOLS.iter().find(|sym| rest.starts_with(**sym)) {
+            i += sym.len();
+            tokens.push((Token::Symbol(sym), line));
+        } else {
+            return Err(anyhow!(
+                "{}:{} : unexpected character '{}'",
+                source,
+                line,
+                c
+            ));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    pos:    usize,
+    source: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn chip(&mut self) -> Result<ChipDef> {
+        self.keyword("CHIP")?;
+        let name = self.ident()?;
+        self.symbol("{")?;
+
+        let inputs = if self.accept_keyword("IN") {
+            self.pin_decls()?
+        } else {
+            vec![]
+        };
+        let outputs = if self.accept_keyword("OUT") {
+            self.pin_decls()?
+        } else {
+            vec![]
+        };
+
+        let body = if self.accept_keyword("BUILTIN") {
+            let builtin = self.ident()?;
+            self.symbol(";")?;
+            // `CLOCKED pin, ...;` is implied by the builtin
+            if self.accept_keyword("CLOCKED") {
+                while !self.accept_symbol(";") {
+                    self.next()?;
+                }
+            }
+            Body::Builtin(builtin)
+        } else {
+            self.keyword("PARTS")?;
+            self.symbol(":")?;
+            Body::Parts(self.parts()?)
+        };
+
+        self.symbol("}")?;
+        if let Some((token, line)) = self.tokens.get(self.pos) {
+            return Err(anyhow!(
+                "{}:{} : unexpected {:?} after chip definition",
+                self.source,
+                line,
+                token
+            ));
+        }
+
+        Ok(ChipDef {
+            name,
+            source: self.source.to_string(),
+            inputs,
+            outputs,
+            body,
+        })
+    }
+
+    // a[16], b, c;
+    fn pin_decls(&mut self) -> Result<Vec<PinDecl>> {
+        let mut pins = vec![];
+        loop {
+            let name = self.ident()?;
+            let width = if self.accept_symbol("[") {
+                let width = self.number()?;
+                self.symbol("]")?;
+                width
+            } else {
+                1
+            };
+            if width == 0 || width > 16 {
+                return Err(anyhow!(
+                    "{}:{} : invalid width of pin {} : {}",
+                    self.source,
+                    self.line(),
+                    name,
+                    width
+                ));
+            }
+            pins.push(PinDecl { name, width });
+
+            if self.accept_symbol(";") {
+                return Ok(pins);
+            }
+            self.symbol(",")?;
+        }
+    }
+
+    fn parts(&mut self) -> Result<Vec<Part>> {
+        let mut parts = vec![];
+        while !self.peek_symbol("}") {
+            let line = self.line();
+            let name = self.ident()?;
+            self.symbol("(")?;
+
+            let mut connections = vec![];
+            loop {
+                let pin = self.pin_ref()?;
+                self.symbol("=")?;
+                let signal = if self.accept_keyword("true") {
+                    Signal::True
+                } else if self.accept_keyword("false") {
+                    Signal::False
+                } else {
+                    Signal::Pin(self.pin_ref()?)
+                };
+                connections.push(Connection { pin, signal });
+
+                if self.accept_symbol(")") {
+                    break;
+                }
+                self.symbol(",")?;
+            }
+            self.symbol(";")?;
+
+            parts.push(Part {
+                name,
+                connections,
+                line,
+            });
+        }
+        Ok(parts)
+    }
+
+    fn pin_ref(&mut self) -> Result<PinRef> {
+        let name = self.ident()?;
+        if !self.accept_symbol("[") {
+            return Ok(PinRef { name, range: None });
+        }
+
+        let from = self.number()?;
+        let to = if self.accept_symbol("..") {
+            self.number()?
+        } else {
+            from
+        };
+        self.symbol("]")?;
+
+        if from > to {
+            return Err(anyhow!(
+                "{}:{} : invalid sub bus {}[{}..{}]",
+                self.source,
+                self.line(),
+                name,
+                from,
+                to
+            ));
+        }
+        Ok(PinRef {
+            name,
+            range: Some((from, to)),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| anyhow!("{}:{} : unexpected end of file", self.source, self.line()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Ident(ident) => Ok(ident),
+            token => Err(self.unexpected("identifier", &token)),
+        }
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        match self.next()? {
+            Token::Number(n) => Ok(n),
+            token => Err(self.unexpected("number", &token)),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.accept_keyword(keyword) {
+            return Ok(());
+        }
+        let token = self.next()?;
+        Err(self.unexpected(keyword, &token))
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Ident(ident)) if ident == keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn symbol(&mut self, sym: &str) -> Result<()> {
+        if self.accept_symbol(sym) {
+            return Ok(());
+        }
+        let token = self.next()?;
+        Err(self.unexpected(sym, &token))
+    }
+
+    fn peek_symbol(&self, sym: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(s)) if *s == sym)
+    }
+
+    fn accept_symbol(&mut self, sym: &str) -> bool {
+        let found = self.peek_symbol(sym);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn unexpected(&self, expected: &str, token: &Token) -> anyhow::Error {
+        let found = match token {
+            Token::Ident(ident) => ident.clone(),
+            Token::Number(n) => n.to_string(),
+            Token::Symbol(sym) => sym.to_string(),
+        };
+        // the token is already consumed
+        let line = self
+            .tokens
+            .get(self.pos.saturating_sub(1))
+            .map_or(1, |(_, line)| *line);
+        anyhow!(
+            "{}:{} : expected {} but found '{}'",
+            self.source,
+            line,
+            expected,
+            found
+        )
+    }
+}
@@ -0,0 +1,261 @@
+// simulate a chip: combinational evaluation and tick/tock of clocked chips
+use super::builtin::Builtin;
+use super::chip::{Instance, Library, Netlist, Pins, FALSE, TRUE};
+use super::parser::{ChipDef, PinDecl};
+
+use anyhow::{anyhow, Result};
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+pub struct Simulator {
+    pub name:    String,
+    pub inputs:  Vec<PinDecl>,
+    pub outputs: Vec<PinDecl>,
+    pins:        Pins,          // pins and internal pins of the chip
+    values:      Vec<bool>,     // value of each wire
+    instances:   Vec<Instance>, // builtin chips in evaluation order
+    cycle:       usize,
+    ticked:      bool,
+}
+
+impl Simulator {
+    // load `Chip.hdl`, parts are resolved in the same directory or builtin chips
+    pub fn load(path: &Path) -> Result<Simulator> {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| anyhow!("invalid file name : {}", path.display()))?;
+        let dir = path
+            .parent()
+            .map_or_else(|| Path::new(".").to_path_buf(), |dir| dir.to_path_buf());
+
+        let mut library = Library::new(dir);
+        let def = library.resolve(name)?;
+
+        let mut netlist = Netlist::new();
+        let pins: Pins = def
+            .inputs
+            .iter()
+            .chain(def.outputs.iter())
+            .map(|decl| (decl.name.clone(), netlist.new_wires(decl.width)))
+            .collect();
+        let pins = netlist.instantiate(&mut library, &def, pins, &mut vec![])?;
+
+        let mut sim = Simulator::new(&def, netlist, pins)?;
+        sim.eval();
+
+        Ok(sim)
+    }
+
+    // renumber merged wires and sort builtin chips in dependency order
+    fn new(def: &ChipDef, netlist: Netlist, pins: Pins) -> Result<Simulator> {
+        let mut netlist = netlist;
+        let mut ids = HashMap::new();
+        ids.insert(FALSE, FALSE);
+        ids.insert(TRUE, TRUE);
+
+        let mut renumber = |netlist: &mut Netlist, wires: &[usize]| -> Vec<usize> {
+            wires
+                .iter()
+                .map(|wire| {
+                    let root = netlist.find(*wire);
+                    let next = ids.len();
+                    *ids.entry(root).or_insert(next)
+                })
+                .collect()
+        };
+
+        let pins = pins
+            .into_iter()
+            .map(|(name, wires)| {
+                let wires = renumber(&mut netlist, &wires);
+                (name, wires)
+            })
+            .collect();
+
+        let mut instances = std::mem::take(&mut netlist.instances);
+        for instance in instances.iter_mut() {
+            instance.inputs = instance
+                .inputs
+                .iter()
+                .map(|wires| renumber(&mut netlist, wires))
+                .collect();
+            instance.outputs = instance
+                .outputs
+                .iter()
+                .map(|wires| renumber(&mut netlist, wires))
+                .collect();
+        }
+
+        let mut values = vec![false; ids.len()];
+        values[TRUE] = true;
+
+        Ok(Simulator {
+            name: def.name.clone(),
+            inputs: def.inputs.clone(),
+            outputs: def.outputs.clone(),
+            pins,
+            values,
+            instances: sort(instances)?,
+            cycle: 0,
+            ticked: false,
+        })
+    }
+
+    pub fn set(&mut self, pin: &str, value: u16) -> Result<()> {
+        if !self.inputs.iter().any(|decl| decl.name == pin) {
+            return Err(anyhow!("{} is not an input pin of {}", pin, self.name));
+        }
+
+        let wires = &self.pins[pin];
+        for (i, wire) in wires.iter().enumerate() {
+            self.values[*wire] = value >> i & 1 == 1;
+        }
+        Ok(())
+    }
+
+    // value of the pin or the internal pin
+    pub fn get(&self, pin: &str) -> Result<u16> {
+        let wires = self
+            .pins
+            .get(pin)
+            .ok_or_else(|| anyhow!("unknown pin {} of {}", pin, self.name))?;
+        Ok(read(&self.values, wires))
+    }
+
+    pub fn width(&self, pin: &str) -> Option<usize> {
+        self.pins.get(pin).map(|wires| wires.len())
+    }
+
+    // the first builtin chip named `name` in the chip, e.g. `DRegister` or `RAM16K`
+    pub fn builtin(&self, name: &str) -> Option<&Builtin> {
+        self.instances
+            .iter()
+            .map(|instance| &instance.builtin)
+            .find(|builtin| format!("{:?}", builtin.gate) == name)
+    }
+
+    pub fn builtin_mut(&mut self, name: &str) -> Option<&mut Builtin> {
+        self.instances
+            .iter_mut()
+            .map(|instance| &mut instance.builtin)
+            .find(|builtin| format!("{:?}", builtin.gate) == name)
+    }
+
+    // propagate input values through combinational chips
+    pub fn eval(&mut self) {
+        for instance in &self.instances {
+            let inputs: Vec<u16> = instance
+                .inputs
+                .iter()
+                .map(|wires| read(&self.values, wires))
+                .collect();
+            let outputs = instance.builtin.eval(&inputs);
+
+            for (wires, value) in instance.outputs.iter().zip(outputs) {
+                for (i, wire) in wires.iter().enumerate() {
+                    self.values[*wire] = value >> i & 1 == 1;
+                }
+            }
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.eval();
+        let values = &self.values;
+        for instance in self.instances.iter_mut() {
+            let inputs: Vec<u16> = instance
+                .inputs
+                .iter()
+                .map(|wires| read(values, wires))
+                .collect();
+            instance.builtin.tick(&inputs);
+        }
+        self.ticked = true;
+        self.eval();
+    }
+
+    pub fn tock(&mut self) {
+        self.eval();
+        self.instances
+            .iter_mut()
+            .for_each(|instance| instance.builtin.tock());
+        self.cycle += 1;
+        self.ticked = false;
+        self.eval();
+    }
+
+    // `0`, `0+` (after tick), `1` (after tock), ...
+    pub fn time(&self) -> String {
+        format!("{}{}", self.cycle, if self.ticked { "+" } else { "" })
+    }
+}
+
+fn read(values: &[bool], wires: &[usize]) -> u16 {
+    wires
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (i, wire)| acc | (values[*wire] as u16) << i)
+}
+
+// topological sort by combinational dependencies, loops must go through clocked chips
+fn sort(instances: Vec<Instance>) -> Result<Vec<Instance>> {
+    let mut drivers = HashMap::new();
+    for (index, instance) in instances.iter().enumerate() {
+        for wire in instance.outputs.iter().flatten() {
+            if *wire <= TRUE {
+                return Err(anyhow!(
+                    "{:?} : output is connected to a constant",
+                    instance.builtin.gate
+                ));
+            }
+            if drivers.insert(*wire, index).is_some() {
+                return Err(anyhow!(
+                    "{:?} : pin has multiple sources",
+                    instance.builtin.gate
+                ));
+            }
+        }
+    }
+
+    let mut dependents = vec![vec![]; instances.len()];
+    let mut degrees = vec![0; instances.len()];
+    for (index, instance) in instances.iter().enumerate() {
+        let deps = instance
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(pin, _)| instance.builtin.is_combinational_input(*pin))
+            .flat_map(|(_, wires)| wires.iter().filter_map(|wire| drivers.get(wire)));
+
+        for dep in deps {
+            dependents[*dep].push(index);
+            degrees[index] += 1;
+        }
+    }
+
+    let mut queue: VecDeque<usize> = (0..instances.len()).filter(|i| degrees[*i] == 0).collect();
+    let mut order = Vec::with_capacity(instances.len());
+    while let Some(index) = queue.pop_front() {
+        order.push(index);
+        for dependent in &dependents[index] {
+            degrees[*dependent] -= 1;
+            if degrees[*dependent] == 0 {
+                queue.push_back(*dependent);
+            }
+        }
+    }
+
+    if order.len() < instances.len() {
+        return Err(anyhow!(
+            "combinational loop detected, loops must go through clocked chips"
+        ));
+    }
+
+    let mut instances: Vec<Option<Instance>> = instances.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .filter_map(|index| instances[index].take())
+        .collect())
+}
@@ -0,0 +1,5 @@
+pub mod runner;
+pub mod script;
+pub mod target;
+
+pub use runner::run;
@@ -0,0 +1,35 @@
+extern crate tstrunner;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+/**
+ * 1. Read test scripts (*.tst)
+ * 2. load the chip (*.hdl), the program (*.hack, *.asm) or vm files
+ * 3. execute the script, write output-file
+ * 4. compare each output line with compare-to file and report the first mismatch
+ */
+fn main() {
+    let scripts: Vec<String> = env::args().skip(1).collect();
+    if scripts.is_empty() {
+        println!("usage: tstrunner FILE.tst [FILE.tst ...]");
+        process::exit(1);
+    }
+
+    let mut failed = 0;
+    for script in &scripts {
+        match tstrunner::run(Path::new(script)) {
+            Ok(_) => println!("{} : End of script - Comparison ended successfully", script),
+            Err(err) => {
+                println!("{} : {}", script, err);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        println!("{} of {} scripts failed", failed, scripts.len());
+        process::exit(1);
+    }
+}
@@ -0,0 +1,262 @@
+// execute test scripts, write `output-file` and compare it with `compare-to`
+use super::script::{parse, Command, Condition, Format, OutputSpec, Statement};
+use super::target::{Target, Value};
+
+use anyhow::{anyhow, Result};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct Runner {
+    dir:         PathBuf, // paths in the script are relative to the script
+    target:      Option<Target>,
+    output_list: Vec<OutputSpec>,
+    output_file: Option<PathBuf>,
+    lines:       Vec<String>,         // output lines
+    compare:     Option<Vec<String>>, // lines of compare-to file
+}
+
+// run `X.tst`, returns an error at the first mismatching line against the compare file
+pub fn run(path: &Path) -> Result<()> {
+    let contents =
+        fs::read_to_string(path).map_err(|err| anyhow!("{} : {}", path.display(), err))?;
+    let statements = parse(&contents, &path.display().to_string())?;
+
+    let dir = match path.parent() {
+        Some(dir) if dir.as_os_str().is_empty() => PathBuf::from("."),
+        Some(dir) => dir.to_path_buf(),
+        None => PathBuf::from("."),
+    };
+    let mut runner = Runner::new(dir);
+
+    let result = runner.exec_all(&statements);
+    runner.write_output()?;
+    result
+}
+
+impl Runner {
+    pub fn new(dir: PathBuf) -> Runner {
+        Runner {
+            dir,
+            target: None,
+            output_list: vec![],
+            output_file: None,
+            lines: vec![],
+            compare: None,
+        }
+    }
+
+    pub fn exec_all(&mut self, statements: &[Statement]) -> Result<()> {
+        statements.iter().try_for_each(|statement| {
+            self.exec(&statement.command)
+                .map_err(|err| anyhow!("line {} : {}", statement.line, err))
+        })
+    }
+
+    fn exec(&mut self, command: &Command) -> Result<()> {
+        match command {
+            Command::Load(file) => {
+                let path = match file {
+                    Some(file) => self.resolve(file),
+                    None => self.dir.clone(),
+                };
+                self.target = Some(Target::load(&path)?);
+            }
+            Command::BuiltinLoad(name, file) => {
+                let path = self.resolve(file);
+                self.target()?.load_builtin(name, &path)?;
+            }
+            Command::OutputFile(file) => self.output_file = Some(self.dir.join(file)),
+            Command::CompareTo(file) => {
+                let path = self.resolve(file);
+                let contents = fs::read_to_string(&path)
+                    .map_err(|err| anyhow!("{} : {}", path.display(), err))?;
+                self.compare = Some(contents.lines().map(|line| line.to_string()).collect());
+            }
+            Command::OutputList(specs) => {
+                self.output_list = specs.clone();
+                let header = specs
+                    .iter()
+                    .map(|spec| self.header(spec))
+                    .collect::<Result<Vec<_>>>()?;
+                self.emit(header)?;
+            }
+            Command::Set(var, value) => self.target()?.set(var, *value as u16)?,
+            Command::Eval => self.target()?.eval()?,
+            Command::Tick => self.target()?.tick()?,
+            Command::Tock => self.target()?.tock()?,
+            Command::TickTock => self.target()?.ticktock()?,
+            Command::VmStep => self.target()?.vmstep()?,
+            Command::Output => {
+                let target = self
+                    .target
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("no program is loaded"))?;
+                let columns = self
+                    .output_list
+                    .iter()
+                    .map(|spec| Ok(format_value(spec, &target.get(&spec.var)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                self.emit(columns)?;
+            }
+            Command::Echo(text) => println!("{}", text),
+            Command::ClearEcho => {}
+            Command::Repeat(Some(count), statements) => {
+                (0..*count).try_for_each(|_| self.exec_all(statements))?
+            }
+            Command::Repeat(None, statements) => loop {
+                self.exec_all(statements)?;
+            },
+            Command::While(cond, statements) => {
+                while self.check(cond)? {
+                    self.exec_all(statements)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn target(&mut self) -> Result<&mut Target> {
+        self.target
+            .as_mut()
+            .ok_or_else(|| anyhow!("no program is loaded"))
+    }
+
+    fn check(&mut self, cond: &Condition) -> Result<bool> {
+        let value = match self.target()?.get(&cond.var)? {
+            Value::Number(value, 16) => value as i16 as i32,
+            Value::Number(value, _) => value as i32,
+            Value::Text(_) => return Err(anyhow!("{} is not a number", cond.var)),
+        };
+        Ok(cond.eval(value))
+    }
+
+    // file in the script directory, `Mult.hack` also finds `mult.hack`
+    fn resolve(&self, file: &str) -> PathBuf {
+        let path = self.dir.join(file);
+        if path.exists() {
+            return path;
+        }
+
+        let found = fs::read_dir(&self.dir).ok().and_then(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .find(|entry| {
+                    entry
+                        .file_name()
+                        .to_string_lossy()
+                        .eq_ignore_ascii_case(file)
+                })
+                .map(|entry| entry.path())
+        });
+        found.unwrap_or(path)
+    }
+
+    fn header(&mut self, spec: &OutputSpec) -> Result<String> {
+        let format = match spec.format {
+            Some(format) => format,
+            None => default_format(&self.target()?.get(&spec.var)?),
+        };
+
+        let width = format.left + format.len + format.right;
+        let name: String = spec.var.chars().take(width).collect();
+        let left = (width - name.len()) / 2;
+        Ok(format!(
+            "{}{}{}",
+            " ".repeat(left),
+            name,
+            " ".repeat(width - name.len() - left)
+        ))
+    }
+
+    // append the line to output and compare it with the same line of compare-to file
+    fn emit(&mut self, columns: Vec<String>) -> Result<()> {
+        let line = format!("|{}|", columns.join("|"));
+        self.lines.push(line);
+
+        let compare = match &self.compare {
+            Some(compare) => compare,
+            None => return Ok(()),
+        };
+
+        let lineno = self.lines.len();
+        let actual = &self.lines[lineno - 1];
+        let expected = compare.get(lineno - 1).map_or("", |line| line.as_str());
+        if !matches(expected, actual) {
+            return Err(anyhow!(
+                "comparison failure at line {}\n  expected: {}\n  actual:   {}",
+                lineno,
+                expected.trim_end(),
+                actual
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn write_output(&self) -> Result<()> {
+        if let Some(path) = &self.output_file {
+            let mut contents = self.lines.join("\n");
+            contents.push('\n');
+            fs::write(path, contents).map_err(|err| anyhow!("{} : {}", path.display(), err))?;
+        }
+        Ok(())
+    }
+}
+
+// `*` in the compare file matches any character
+fn matches(expected: &str, actual: &str) -> bool {
+    let expected = expected.trim_end();
+    let actual = actual.trim_end();
+
+    expected.chars().count() == actual.chars().count()
+        && expected
+            .chars()
+            .zip(actual.chars())
+            .all(|(e, a)| e == '*' || e == a)
+}
+
+fn default_format(value: &Value) -> Format {
+    match value {
+        Value::Number(_, width) => Format {
+            kind:  'B',
+            left:  1,
+            len:   *width,
+            right: 1,
+        },
+        Value::Text(text) => Format {
+            kind:  'S',
+            left:  1,
+            len:   text.len(),
+            right: 1,
+        },
+    }
+}
+
+fn format_value(spec: &OutputSpec, value: &Value) -> String {
+    let format = spec.format.unwrap_or_else(|| default_format(value));
+    let len = format.len;
+
+    let text = match (format.kind, value) {
+        (_, Value::Text(text)) => format!("{:<len$}", text, len = len),
+        ('B', Value::Number(n, _)) => {
+            let bits = format!("{:016b}", n);
+            format!("{:0>len$}", &bits[16 - len.min(16)..], len = len)
+        }
+        ('X', Value::Number(n, _)) => {
+            let digits = format!("{:04X}", n);
+            format!("{:0>len$}", &digits[4 - len.min(4)..], len = len)
+        }
+        ('S', Value::Number(n, _)) => format!("{:<len$}", n, len = len),
+        (_, Value::Number(n, 16)) => format!("{:>len$}", *n as i16, len = len),
+        (_, Value::Number(n, _)) => format!("{:>len$}", n, len = len),
+    };
+
+    format!(
+        "{}{}{}",
+        " ".repeat(format.left),
+        text,
+        " ".repeat(format.right)
+    )
+}
@@ -0,0 +1,353 @@
+// parser for test scripts (*.tst)
+//
+//   load Foo.hdl,
+//   output-file Foo.out,
+//   compare-to Foo.cmp,
+//   output-list a%B3.1.3 out%D1.6.1;
+//   set a 1, eval, output;
+//   repeat 3 { tick, tock, output; }
+use anyhow::{anyhow, Result};
+
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub command: Command,
+    pub line:    usize, // line number in the script
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Load(Option<String>),        // `load` without file loads the directory
+    BuiltinLoad(String, String), // `ROM32K load Prog.hack`
+    OutputFile(String),
+    CompareTo(String),
+    OutputList(Vec<OutputSpec>),
+    Set(String, i32),
+    Eval,
+    Tick,
+    Tock,
+    TickTock,
+    VmStep,
+    Output,
+    Echo(String),
+    ClearEcho,
+    Repeat(Option<usize>, Vec<Statement>), // `repeat { ... }` repeats forever
+    While(Condition, Vec<Statement>),
+}
+
+// `var%B1.16.1` : left padding, length and right padding
+#[derive(Debug, Clone)]
+pub struct OutputSpec {
+    pub var:    String,
+    pub format: Option<Format>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Format {
+    pub kind:  char, // B, D, X or S
+    pub left:  usize,
+    pub len:   usize,
+    pub right: usize,
+}
+
+// `var <> 89`
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub var:   String,
+    pub op:    String,
+    pub value: i32,
+}
+
+impl Condition {
+    pub fn eval(&self, value: i32) -> bool {
+        match self.op.as_str() {
+            "=" => value == self.value,
+            "<>" => value != self.value,
+            "<" => value < self.value,
+            ">" => value > self.value,
+            "<=" => value <= self.value,
+            _ => value >= self.value,
+        }
+    }
+}
+
+const OPERATORS: [&str; 6] = ["=", "<>", "<", ">", "<=", ">="];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String), // "quoted string"
+    Symbol(char), // { } , ; !
+}
+
+pub fn parse(contents: &str, source: &str) -> Result<Vec<Statement>> {
+    let tokens = tokenize(contents, source)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        source,
+    };
+
+    let statements = parser.statements()?;
+    if let Some((_, line)) = parser.tokens.get(parser.pos) {
+        return Err(anyhow!("{}:{} : unexpected '}}'", source, line));
+    }
+    Ok(statements)
+}
+
+fn tokenize(contents: &str, source: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = contents.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            let start = line;
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(anyhow!("{}:{} : unterminated comment", source, start));
+            }
+            i += 2;
+        } else if c == '"' {
+            let text: String = chars[i + 1..]
+                .iter()
+                .take_while(|c| **c != '"' && **c != '\n')
+                .collect();
+            i += text.chars().count() + 1;
+            if chars.get(i) != Some(&'"') {
+                return Err(anyhow!("{}:{} : unterminated string", source, line));
+            }
+            i += 1;
+            tokens.push((Token::Text(text), line));
+        } else if "{},;!".contains(c) {
+            tokens.push((Token::Symbol(c), line));
+            i += 1;
+        } else {
+            let word: String = chars[i..]
+                .iter()
+                .take_while(|c| !c.is_whitespace() && !"{},;!\"".contains(**c))
+                .collect();
+            i += word.chars().count();
+            tokens.push((Token::Word(word), line));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    pos:    usize,
+    source: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    // statements until `}` or the end of the script
+    fn statements(&mut self) -> Result<Vec<Statement>> {
+        let mut statements = vec![];
+        loop {
+            match self.peek() {
+                None | Some(Token::Symbol('}')) => return Ok(statements),
+                Some(Token::Symbol(_)) => self.pos += 1, // empty statement
+                _ => statements.push(self.statement()?),
+            }
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        let line = self.line();
+        let name = self.word()?;
+
+        let command = match name.as_str() {
+            "load" => Command::Load(self.accept_word()),
+            "output-file" => Command::OutputFile(self.word()?),
+            "compare-to" => Command::CompareTo(self.word()?),
+            "output-list" => {
+                let mut specs = vec![];
+                while let Some(word) = self.accept_word() {
+                    specs.push(self.output_spec(&word)?);
+                }
+                Command::OutputList(specs)
+            }
+            "set" => {
+                let var = self.word()?;
+                let value = self.word()?;
+                Command::Set(var, self.value(&value)?)
+            }
+            "eval" => Command::Eval,
+            "tick" => Command::Tick,
+            "tock" => Command::Tock,
+            "ticktock" => Command::TickTock,
+            "vmstep" => Command::VmStep,
+            "output" => Command::Output,
+            "echo" => match self.next()? {
+                Token::Text(text) | Token::Word(text) => Command::Echo(text),
+                Token::Symbol(c) => {
+                    return Err(self.error(&format!("expected text but found '{}'", c)))
+                }
+            },
+            "clear-echo" => Command::ClearEcho,
+            "repeat" => {
+                let count = match self.accept_word() {
+                    Some(n) => Some(
+                        n.parse::<usize>()
+                            .map_err(|_| self.error(&format!("invalid repeat count {}", n)))?,
+                    ),
+                    None => None,
+                };
+                Command::Repeat(count, self.block()?)
+            }
+            "while" => {
+                let var = self.word()?;
+                let op = self.word()?;
+                if !OPERATORS.contains(&op.as_str()) {
+                    return Err(self.error(&format!("unknown operator {}", op)));
+                }
+                let value = self.word()?;
+                let cond = Condition {
+                    var,
+                    op,
+                    value: self.value(&value)?,
+                };
+                Command::While(cond, self.block()?)
+            }
+            // `ROM32K load Prog.hack`
+            _ if self.accept_keyword("load") => Command::BuiltinLoad(name, self.word()?),
+            _ => return Err(self.error(&format!("unknown command {}", name))),
+        };
+
+        if !matches!(command, Command::Repeat(..) | Command::While(..)) {
+            match self.peek() {
+                Some(Token::Symbol(',')) | Some(Token::Symbol(';')) | Some(Token::Symbol('!')) => {
+                    self.pos += 1
+                }
+                Some(Token::Symbol('}')) | None => {}
+                _ => return Err(self.error("expected ',' or ';'")),
+            }
+        }
+
+        Ok(Statement { command, line })
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>> {
+        if self.next()? != Token::Symbol('{') {
+            return Err(self.error("expected '{'"));
+        }
+        let statements = self.statements()?;
+        if self.next()? != Token::Symbol('}') {
+            return Err(self.error("expected '}'"));
+        }
+        Ok(statements)
+    }
+
+    // `var%B1.16.1` or `var`
+    fn output_spec(&self, word: &str) -> Result<OutputSpec> {
+        let (var, format) = match word.split_once('%') {
+            Some((var, format)) => (var, format),
+            None => {
+                return Ok(OutputSpec {
+                    var:    word.to_string(),
+                    format: None,
+                })
+            }
+        };
+
+        let kind = format.chars().next().filter(|c| "BDXS".contains(*c));
+        let paddings = format
+            .get(1..)
+            .unwrap_or("")
+            .split('.')
+            .map(|n| n.parse::<usize>())
+            .collect::<std::result::Result<Vec<_>, _>>();
+
+        match (kind, paddings) {
+            (Some(kind), Ok(paddings)) if paddings.len() == 3 => Ok(OutputSpec {
+                var:    var.to_string(),
+                format: Some(Format {
+                    kind,
+                    left: paddings[0],
+                    len: paddings[1],
+                    right: paddings[2],
+                }),
+            }),
+            _ => Err(self.error(&format!("invalid output format {}", word))),
+        }
+    }
+
+    // `%B0101`, `%XFF`, `%D-1` or `-1`
+    fn value(&self, word: &str) -> Result<i32> {
+        let parsed = match word.get(..2) {
+            Some("%B") => i32::from_str_radix(&word[2..], 2),
+            Some("%X") => i32::from_str_radix(&word[2..], 16),
+            Some("%D") => word[2..].parse::<i32>(),
+            _ => word.parse::<i32>(),
+        };
+        parsed.map_err(|_| self.error(&format!("invalid value {}", word)))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| self.error("unexpected end of script"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn word(&mut self) -> Result<String> {
+        self.accept_word()
+            .ok_or_else(|| self.error("missing argument"))
+    }
+
+    fn accept_word(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                let word = word.clone();
+                self.pos += 1;
+                Some(word)
+            }
+            _ => None,
+        }
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(word)) if word == keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, msg: &str) -> anyhow::Error {
+        anyhow!("{}:{} : {}", self.source, self.line(), msg)
+    }
+}
@@ -0,0 +1,203 @@
+// program under test: a chip, a hack program or vm files
+use cpuemulator::{loader, Cpu};
+use hdlsimulator::Simulator;
+use vmemulator::vm::{self, Vm};
+
+use anyhow::{anyhow, Result};
+
+use std::path::Path;
+
+pub enum Target {
+    Chip(Simulator), // *.hdl
+    Cpu(Cpu),        // *.hack or *.asm
+    Vm(Vm),          // *.vm or a directory of *.vm
+}
+
+// value of a variable in output-list
+pub enum Value {
+    Number(u16, usize), // (value, width in bits)
+    Text(String),
+}
+
+impl Target {
+    pub fn load(path: &Path) -> Result<Target> {
+        let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        match ext {
+            "hdl" => Ok(Target::Chip(Simulator::load(path)?)),
+            "hack" | "asm" => Ok(Target::Cpu(Cpu::new(loader::load_file(path)?))),
+            _ => {
+                let results = vmemulator::load(&path.display().to_string())?;
+                Ok(Target::Vm(Vm::from_results(results)?))
+            }
+        }
+    }
+
+    pub fn get(&self, var: &str) -> Result<Value> {
+        let (name, index) = split_index(var)?;
+
+        match self {
+            Target::Chip(sim) if var == "time" => Ok(Value::Text(sim.time())),
+            // `DRegister[]`, `RAM16K[1]` or `a[3]`
+            Target::Chip(sim) => match (sim.builtin(name), index) {
+                (Some(builtin), Some(index)) => {
+                    Ok(Value::Number(builtin.get(index.unwrap_or(0))?, 16))
+                }
+                (_, Some(Some(bit))) => Ok(Value::Number(sim.get(name)? >> bit & 1, 1)),
+                (_, None) => Ok(Value::Number(sim.get(name)?, sim.width(name).unwrap_or(16))),
+                _ => Err(anyhow!("unknown variable {}", var)),
+            },
+            Target::Cpu(cpu) => match (name, index) {
+                ("time", None) => Ok(Value::Text(cpu.time.to_string())),
+                ("A", None) => Ok(Value::Number(cpu.a, 16)),
+                ("D", None) => Ok(Value::Number(cpu.d, 16)),
+                ("PC", None) => Ok(Value::Number(cpu.pc, 16)),
+                ("RAM", Some(Some(addr))) => Ok(Value::Number(cpu.read_ram(addr)?, 16)),
+                ("ROM", Some(Some(addr))) => Ok(Value::Number(cpu.rom.fetch(addr as u16), 16)),
+                _ => Err(anyhow!("unknown variable {}", var)),
+            },
+            Target::Vm(vm) => match (name, index) {
+                ("currentFunction", None) => {
+                    Ok(Value::Text(vm.current_function().unwrap_or("").to_string()))
+                }
+                ("line", None) => Ok(Value::Text(vm.pc.to_string())),
+                _ => Ok(Value::Number(
+                    vm.read_ram(vm_addr(vm, name, index, var)?)? as u16,
+                    16,
+                )),
+            },
+        }
+    }
+
+    pub fn set(&mut self, var: &str, value: u16) -> Result<()> {
+        let (name, index) = split_index(var)?;
+
+        match self {
+            Target::Chip(sim) => match index {
+                Some(index) => {
+                    let builtin = sim
+                        .builtin_mut(name)
+                        .ok_or_else(|| anyhow!("unknown variable {}", var))?;
+                    builtin.set(index.unwrap_or(0), value)
+                }
+                None => sim.set(name, value),
+            },
+            Target::Cpu(cpu) => {
+                match (name, index) {
+                    ("A", None) => cpu.a = value,
+                    ("D", None) => cpu.d = value,
+                    ("PC", None) => cpu.pc = value,
+                    ("RAM", Some(Some(addr))) => cpu.write_ram(addr, value)?,
+                    _ => return Err(anyhow!("unknown variable {}", var)),
+                }
+                Ok(())
+            }
+            Target::Vm(vm) => {
+                let addr = vm_addr(vm, name, index, var)?;
+                vm.write_ram(addr, value as i16)
+            }
+        }
+    }
+
+    // `ROM32K load Prog.hack`
+    pub fn load_builtin(&mut self, name: &str, path: &Path) -> Result<()> {
+        let sim = match self {
+            Target::Chip(sim) => sim,
+            _ => return Err(anyhow!("{} load is supported only for chips", name)),
+        };
+        let rom = loader::load_file(path)?;
+        let chip = sim.name.clone();
+        let builtin = sim
+            .builtin_mut(name)
+            .ok_or_else(|| anyhow!("{} has no part {}", chip, name))?;
+        builtin.load(rom.words())
+    }
+
+    pub fn eval(&mut self) -> Result<()> {
+        match self {
+            Target::Chip(sim) => {
+                sim.eval();
+                Ok(())
+            }
+            _ => Err(anyhow!("eval is supported only for chips")),
+        }
+    }
+
+    pub fn tick(&mut self) -> Result<()> {
+        match self {
+            Target::Chip(sim) => {
+                sim.tick();
+                Ok(())
+            }
+            _ => Err(anyhow!("tick is supported only for chips")),
+        }
+    }
+
+    pub fn tock(&mut self) -> Result<()> {
+        match self {
+            Target::Chip(sim) => {
+                sim.tock();
+                Ok(())
+            }
+            _ => Err(anyhow!("tock is supported only for chips")),
+        }
+    }
+
+    pub fn ticktock(&mut self) -> Result<()> {
+        match self {
+            Target::Chip(sim) => {
+                sim.tick();
+                sim.tock();
+                Ok(())
+            }
+            Target::Cpu(cpu) => cpu.step(),
+            Target::Vm(_) => Err(anyhow!("ticktock is not supported for vm, use vmstep")),
+        }
+    }
+
+    pub fn vmstep(&mut self) -> Result<()> {
+        match self {
+            Target::Vm(vm) => vm.step(),
+            _ => Err(anyhow!("vmstep is supported only for vm")),
+        }
+    }
+}
+
+// `RAM[16]` => ("RAM", Some(Some(16))), `PC[]` => ("PC", Some(None)), `out` => ("out", None)
+fn split_index(var: &str) -> Result<(&str, Option<Option<usize>>)> {
+    let (name, rest) = match var.split_once('[') {
+        Some(split) => split,
+        None => return Ok((var, None)),
+    };
+
+    let index = rest
+        .strip_suffix(']')
+        .ok_or_else(|| anyhow!("invalid variable {}", var))?;
+    if index.is_empty() {
+        return Ok((name, Some(None)));
+    }
+
+    let index = index
+        .parse::<usize>()
+        .map_err(|_| anyhow!("invalid index {}", var))?;
+    Ok((name, Some(Some(index))))
+}
+
+// RAM address of vm variables, `sp`, `local`, `argument[1]`, `temp[0]`, `RAM[256]`...
+fn vm_addr(vm: &Vm, name: &str, index: Option<Option<usize>>, var: &str) -> Result<usize> {
+    let reg = match name {
+        "sp" => Some(vm::SP),
+        "local" => Some(vm::LCL),
+        "argument" => Some(vm::ARG),
+        "this" => Some(vm::THIS),
+        "that" => Some(vm::THAT),
+        _ => None,
+    };
+
+    match (name, reg, index) {
+        (_, Some(reg), None) => Ok(reg),
+        (_, Some(reg), Some(Some(i))) => Ok(vm.reg(reg)? as usize + i),
+        ("temp", _, Some(Some(i))) if i < 8 => Ok(vm::TEMP + i),
+        ("RAM", _, Some(Some(addr))) => Ok(addr),
+        _ => Err(anyhow!("unknown variable {}", var)),
+    }
+}
@@ -0,0 +1,17 @@
+pub mod os;
+pub mod program;
+pub mod vm;
+
+pub use program::Program;
+pub use vm::Vm;
+
+use vmtranslator::parser::{parse, ParseResult};
+use vmtranslator::source::read_sources;
+
+use anyhow::Result;
+
+// read `*.vm` file or all `*.vm` files in the directory and parse them
+pub fn load(arg: &str) -> Result<Vec<ParseResult>> {
+    let sources = read_sources(arg)?;
+    Ok(sources.map(|src| parse(&src.code, &src.vm_name)).collect())
+}
@@ -0,0 +1,102 @@
+extern crate vmemulator;
+
+use vmemulator::Vm;
+
+use getopts::Options;
+
+use std::env;
+use std::process;
+
+const DEFAULT_MAX_STEPS: usize = 1_000_000;
+
+struct Config {
+    target: String,
+    max_steps: usize,
+    dumps: Vec<(usize, usize)>,
+}
+
+/**
+ * 1. Read file or directory
+ * 2. parse each vm files to VMCommand(s)
+ * 3. run from Sys.init (or the first command) until halt or max steps
+ * 4. print output and RAM
+ */
+fn main() {
+    let config = parse_args();
+
+    let results = vmemulator::load(&config.target).unwrap_or_else(|err| {
+        println!("cannot read file: {}", err);
+        process::exit(1);
+    });
+
+    let mut vm = Vm::from_results(results).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(1);
+    });
+
+    let steps = vm.run(config.max_steps).unwrap_or_else(|err| {
+        println!("runtime error in {} : {}", vm.current_function().unwrap_or("?"), err);
+        process::exit(1);
+    });
+
+    if !vm.os.output.is_empty() {
+        println!("{}", vm.os.output);
+    }
+
+    if vm.is_halted() {
+        println!("halted after {} steps", steps);
+    } else {
+        println!("stopped after {} steps (max steps reached)", steps);
+    }
+
+    config.dumps.iter().for_each(|(from, to)| {
+        (*from..=*to).for_each(|addr| match vm.read_ram(addr) {
+            Ok(value) => println!("RAM[{}] = {}", addr, value),
+            Err(err) => println!("{}", err),
+        })
+    });
+}
+
+fn parse_args() -> Config {
+    let args: Vec<String> = env::args().collect();
+    let mut opts = Options::new();
+
+    opts.optopt("s", "steps", "max number of vm commands to run (default 1000000)", "N");
+    opts.optmulti("d", "dump", "print RAM after running", "FROM[:TO]");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            process::exit(1);
+        }
+    };
+
+    let max_steps = matches.opt_str("steps").map_or(DEFAULT_MAX_STEPS, |n| {
+        n.parse::<usize>().unwrap_or_else(|err| {
+            println!("invalid steps: {}, {}", n, err);
+            process::exit(1);
+        })
+    });
+
+    let dumps = matches.opt_strs("dump").iter().map(|s| parse_range(s)).collect();
+
+    let target = if !matches.free.is_empty() {
+        matches.free[0].clone()
+    } else {
+        println!("{}", opts.usage("usage: vmemulator [options] FILE.vm|DIR"));
+        process::exit(1);
+    };
+
+    Config { target, max_steps, dumps }
+}
+
+fn parse_range(s: &str) -> (usize, usize) {
+    let (from, to) = s.split_once(':').unwrap_or((s, s));
+    let parsed = from.parse::<usize>().and_then(|from| to.parse::<usize>().map(|to| (from, to)));
+
+    parsed.unwrap_or_else(|_| {
+        println!("invalid --dump, expected FROM[:TO]: {}", s);
+        process::exit(1);
+    })
+}
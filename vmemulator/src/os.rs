@@ -0,0 +1,469 @@
+// Jack OS functions implemented in rust.
+// these are called only when no vm file defines the function, so the OS written in jack (projects 12) always wins.
+use crate::vm::{NativeFn, Vm};
+
+use cpuemulator::memory::{SCREEN, SCREEN_HEIGHT, SCREEN_SIZE, SCREEN_WIDTH};
+
+use anyhow::{anyhow, Result};
+
+use std::collections::HashMap;
+
+pub const HEAP_BASE: usize = 2048;
+pub const HEAP_END: usize = SCREEN;
+
+// character codes of the Jack character set
+const NEWLINE: i16 = 128;
+const BACKSPACE: i16 = 129;
+const DOUBLE_QUOTE: i16 = 34;
+
+// state of the native OS
+pub struct Os {
+    pub output: String, // text printed by Output.*
+    color: bool,
+    free: Vec<(usize, usize)>, // free heap blocks (addr, size), sorted by addr
+    allocated: HashMap<usize, usize>,
+}
+
+impl Default for Os {
+    fn default() -> Self {
+        Os::new()
+    }
+}
+
+impl Os {
+    pub fn new() -> Os {
+        Os {
+            output: String::new(),
+            color: true,
+            free: vec![(HEAP_BASE, HEAP_END - HEAP_BASE)],
+            allocated: HashMap::new(),
+        }
+    }
+
+    // first-fit allocation
+    pub fn alloc(&mut self, size: usize) -> Result<usize> {
+        let pos = self
+            .free
+            .iter()
+            .position(|(_, len)| *len >= size)
+            .ok_or_else(|| anyhow!("heap overflow : {} words", size))?;
+
+        let (addr, len) = self.free[pos];
+        if len == size {
+            self.free.remove(pos);
+        } else {
+            self.free[pos] = (addr + size, len - size);
+        }
+        self.allocated.insert(addr, size);
+
+        Ok(addr)
+    }
+
+    pub fn dealloc(&mut self, addr: usize) -> Result<()> {
+        let size = self.allocated.remove(&addr).ok_or_else(|| anyhow!("deAlloc of unallocated block : {}", addr))?;
+
+        let pos = self.free.iter().position(|(a, _)| *a > addr).unwrap_or(self.free.len());
+        self.free.insert(pos, (addr, size));
+
+        // coalesce with the next and the previous block
+        if pos + 1 < self.free.len() && self.free[pos].0 + self.free[pos].1 == self.free[pos + 1].0 {
+            self.free[pos].1 += self.free.remove(pos + 1).1;
+        }
+        if pos > 0 && self.free[pos - 1].0 + self.free[pos - 1].1 == self.free[pos].0 {
+            self.free[pos - 1].1 += self.free.remove(pos).1;
+        }
+
+        Ok(())
+    }
+
+    fn print_char(&mut self, c: i16) {
+        match c {
+            NEWLINE => self.output.push('\n'),
+            BACKSPACE => {
+                self.output.pop();
+            }
+            c => self.output.push((c as u8) as char),
+        }
+    }
+}
+
+const NATIVES: &[(&str, NativeFn)] = &[
+    // Math
+    ("Math.init", noop),
+    ("Math.multiply", math_multiply),
+    ("Math.divide", math_divide),
+    ("Math.min", math_min),
+    ("Math.max", math_max),
+    ("Math.abs", math_abs),
+    ("Math.sqrt", math_sqrt),
+    // Memory
+    ("Memory.init", noop),
+    ("Memory.peek", memory_peek),
+    ("Memory.poke", memory_poke),
+    ("Memory.alloc", memory_alloc),
+    ("Memory.deAlloc", memory_dealloc),
+    // Array
+    ("Array.new", memory_alloc),
+    ("Array.dispose", memory_dealloc),
+    // String
+    ("String.new", string_new),
+    ("String.dispose", memory_dealloc),
+    ("String.length", string_length),
+    ("String.charAt", string_char_at),
+    ("String.setCharAt", string_set_char_at),
+    ("String.appendChar", string_append_char),
+    ("String.eraseLastChar", string_erase_last_char),
+    ("String.intValue", string_int_value),
+    ("String.setInt", string_set_int),
+    ("String.newLine", string_new_line),
+    ("String.backSpace", string_back_space),
+    ("String.doubleQuote", string_double_quote),
+    // Output
+    ("Output.init", noop),
+    ("Output.moveCursor", noop),
+    ("Output.printChar", output_print_char),
+    ("Output.printString", output_print_string),
+    ("Output.printInt", output_print_int),
+    ("Output.println", output_println),
+    ("Output.backSpace", output_back_space),
+    // Screen
+    ("Screen.init", noop),
+    ("Screen.clearScreen", screen_clear_screen),
+    ("Screen.setColor", screen_set_color),
+    ("Screen.drawPixel", screen_draw_pixel),
+    ("Screen.drawLine", screen_draw_line),
+    ("Screen.drawRectangle", screen_draw_rectangle),
+    ("Screen.drawCircle", screen_draw_circle),
+    // Keyboard
+    ("Keyboard.init", noop),
+    ("Keyboard.keyPressed", keyboard_key_pressed),
+    // Sys
+    ("Sys.init", sys_init),
+    ("Sys.halt", sys_halt),
+    ("Sys.error", sys_error),
+    ("Sys.wait", noop),
+];
+
+pub fn install(vm: &mut Vm) {
+    NATIVES.iter().for_each(|(name, f)| vm.register_native(name, *f));
+}
+
+fn noop(_vm: &mut Vm, _args: &[i16]) -> Result<i16> {
+    Ok(0)
+}
+
+fn arg(args: &[i16], n: usize) -> Result<i16> {
+    args.get(n)
+        .copied()
+        .ok_or_else(|| anyhow!("expected at least {} arguments but {}", n + 1, args.len()))
+}
+
+fn addr(args: &[i16], n: usize) -> Result<usize> {
+    arg(args, n).map(|v| v as u16 as usize)
+}
+
+fn math_multiply(_vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    Ok(arg(args, 0)?.wrapping_mul(arg(args, 1)?))
+}
+
+fn math_divide(_vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let y = arg(args, 1)?;
+    if y == 0 {
+        return Err(anyhow!("division by zero"));
+    }
+    Ok(arg(args, 0)?.wrapping_div(y))
+}
+
+fn math_min(_vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    Ok(arg(args, 0)?.min(arg(args, 1)?))
+}
+
+fn math_max(_vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    Ok(arg(args, 0)?.max(arg(args, 1)?))
+}
+
+fn math_abs(_vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    Ok(arg(args, 0)?.wrapping_abs())
+}
+
+fn math_sqrt(_vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let x = arg(args, 0)?;
+    if x < 0 {
+        return Err(anyhow!("cannot compute square root of a negative number : {}", x));
+    }
+
+    let mut y: i32 = 0;
+    while (y + 1) * (y + 1) <= x as i32 {
+        y += 1;
+    }
+    Ok(y as i16)
+}
+
+fn memory_peek(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    vm.read_ram(addr(args, 0)?)
+}
+
+fn memory_poke(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    vm.write_ram(addr(args, 0)?, arg(args, 1)?)?;
+    Ok(0)
+}
+
+fn memory_alloc(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let size = arg(args, 0)?;
+    if size <= 0 {
+        return Err(anyhow!("allocated memory size must be positive : {}", size));
+    }
+    vm.os.alloc(size as usize).map(|addr| addr as i16)
+}
+
+fn memory_dealloc(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    vm.os.dealloc(addr(args, 0)?)?;
+    Ok(0)
+}
+
+// String object layout: [max length, length, chars...]
+fn string_new(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let max = arg(args, 0)?;
+    if max < 0 {
+        return Err(anyhow!("maximum length must be non-negative : {}", max));
+    }
+
+    let this = vm.os.alloc(max as usize + 2)?;
+    vm.write_ram(this, max)?;
+    vm.write_ram(this + 1, 0)?;
+
+    Ok(this as i16)
+}
+
+fn string_length(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    vm.read_ram(addr(args, 0)? + 1)
+}
+
+fn string_index(vm: &mut Vm, this: usize, j: i16) -> Result<usize> {
+    let len = vm.read_ram(this + 1)?;
+    if j < 0 || j >= len {
+        return Err(anyhow!("string index out of bounds : {}", j));
+    }
+    Ok(this + 2 + j as usize)
+}
+
+fn string_char_at(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let index = string_index(vm, addr(args, 0)?, arg(args, 1)?)?;
+    vm.read_ram(index)
+}
+
+fn string_set_char_at(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let index = string_index(vm, addr(args, 0)?, arg(args, 1)?)?;
+    vm.write_ram(index, arg(args, 2)?)?;
+    Ok(0)
+}
+
+fn string_append_char(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let this = addr(args, 0)?;
+    let max = vm.read_ram(this)?;
+    let len = vm.read_ram(this + 1)?;
+    if len >= max {
+        return Err(anyhow!("string is full"));
+    }
+
+    vm.write_ram(this + 2 + len as usize, arg(args, 1)?)?;
+    vm.write_ram(this + 1, len + 1)?;
+
+    Ok(this as i16)
+}
+
+fn string_erase_last_char(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let this = addr(args, 0)?;
+    let len = vm.read_ram(this + 1)?;
+    if len == 0 {
+        return Err(anyhow!("string is empty"));
+    }
+
+    vm.write_ram(this + 1, len - 1)?;
+    Ok(0)
+}
+
+fn read_string(vm: &mut Vm, this: usize) -> Result<Vec<i16>> {
+    let len = vm.read_ram(this + 1)?.max(0) as usize;
+    (0..len).map(|j| vm.read_ram(this + 2 + j)).collect()
+}
+
+fn string_int_value(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let chars = read_string(vm, addr(args, 0)?)?;
+    let (neg, digits) = match chars.split_first() {
+        Some((&c, rest)) if c == '-' as i16 => (true, rest),
+        _ => (false, &chars[..]),
+    };
+
+    let value = digits
+        .iter()
+        .take_while(|&&c| ('0' as i16..='9' as i16).contains(&c))
+        .fold(0i16, |acc, &c| acc.wrapping_mul(10).wrapping_add(c - '0' as i16));
+
+    Ok(if neg { value.wrapping_neg() } else { value })
+}
+
+fn string_set_int(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let this = addr(args, 0)?;
+    let digits = arg(args, 1)?.to_string();
+
+    let max = vm.read_ram(this)?;
+    if digits.len() > max.max(0) as usize {
+        return Err(anyhow!("string is too short for {}", digits));
+    }
+
+    for (j, c) in digits.chars().enumerate() {
+        vm.write_ram(this + 2 + j, c as i16)?;
+    }
+    vm.write_ram(this + 1, digits.len() as i16)?;
+
+    Ok(0)
+}
+
+fn string_new_line(_vm: &mut Vm, _args: &[i16]) -> Result<i16> {
+    Ok(NEWLINE)
+}
+
+fn string_back_space(_vm: &mut Vm, _args: &[i16]) -> Result<i16> {
+    Ok(BACKSPACE)
+}
+
+fn string_double_quote(_vm: &mut Vm, _args: &[i16]) -> Result<i16> {
+    Ok(DOUBLE_QUOTE)
+}
+
+// Output writes to the text buffer `Os::output`, not to the screen
+fn output_print_char(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    vm.os.print_char(arg(args, 0)?);
+    Ok(0)
+}
+
+fn output_print_string(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let chars = read_string(vm, addr(args, 0)?)?;
+    chars.into_iter().for_each(|c| vm.os.print_char(c));
+    Ok(0)
+}
+
+fn output_print_int(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let value = arg(args, 0)?;
+    vm.os.output.push_str(&value.to_string());
+    Ok(0)
+}
+
+fn output_println(vm: &mut Vm, _args: &[i16]) -> Result<i16> {
+    vm.os.print_char(NEWLINE);
+    Ok(0)
+}
+
+fn output_back_space(vm: &mut Vm, _args: &[i16]) -> Result<i16> {
+    vm.os.print_char(BACKSPACE);
+    Ok(0)
+}
+
+fn screen_clear_screen(vm: &mut Vm, _args: &[i16]) -> Result<i16> {
+    (SCREEN..SCREEN + SCREEN_SIZE).try_for_each(|addr| vm.write_ram(addr, 0))?;
+    Ok(0)
+}
+
+fn screen_set_color(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    vm.os.color = arg(args, 0)? != 0;
+    Ok(0)
+}
+
+fn draw_pixel(vm: &mut Vm, x: i32, y: i32) -> Result<()> {
+    if x < 0 || y < 0 || x >= SCREEN_WIDTH as i32 || y >= SCREEN_HEIGHT as i32 {
+        return Err(anyhow!("illegal pixel coordinates : ({}, {})", x, y));
+    }
+
+    let addr = SCREEN + y as usize * SCREEN_WIDTH / 16 + x as usize / 16;
+    let mask = 1i16 << (x % 16);
+    let word = vm.read_ram(addr)?;
+    let word = if vm.os.color { word | mask } else { word & !mask };
+
+    vm.write_ram(addr, word)
+}
+
+fn coords(args: &[i16], n: usize) -> Result<Vec<i32>> {
+    (0..n).map(|i| arg(args, i).map(|v| v as i32)).collect()
+}
+
+fn screen_draw_pixel(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let c = coords(args, 2)?;
+    draw_pixel(vm, c[0], c[1])?;
+    Ok(0)
+}
+
+fn screen_draw_line(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let c = coords(args, 4)?;
+    let (mut x, mut y, x2, y2) = (c[0], c[1], c[2], c[3]);
+
+    // bresenham's line algorithm
+    let dx = (x2 - x).abs();
+    let dy = -(y2 - y).abs();
+    let sx = if x < x2 { 1 } else { -1 };
+    let sy = if y < y2 { 1 } else { -1 };
+    let mut err = dx + dy;
+
+    loop {
+        draw_pixel(vm, x, y)?;
+        if x == x2 && y == y2 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+
+    Ok(0)
+}
+
+fn screen_draw_rectangle(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let c = coords(args, 4)?;
+    for y in c[1]..=c[3] {
+        for x in c[0]..=c[2] {
+            draw_pixel(vm, x, y)?;
+        }
+    }
+    Ok(0)
+}
+
+fn screen_draw_circle(vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    let c = coords(args, 3)?;
+    let (cx, cy, r) = (c[0], c[1], c[2]);
+    if r < 0 {
+        return Err(anyhow!("illegal radius : {}", r));
+    }
+
+    for dy in -r..=r {
+        let dx = ((r * r - dy * dy) as f64).sqrt() as i32;
+        for x in (cx - dx)..=(cx + dx) {
+            draw_pixel(vm, x, cy + dy)?;
+        }
+    }
+    Ok(0)
+}
+
+fn keyboard_key_pressed(vm: &mut Vm, _args: &[i16]) -> Result<i16> {
+    Ok(vm.ram.key() as i16)
+}
+
+// the OS is initialized natively, so only Main.main is left to call
+fn sys_init(vm: &mut Vm, _args: &[i16]) -> Result<i16> {
+    vm.tail_call("Main.main");
+    Ok(0)
+}
+
+fn sys_halt(vm: &mut Vm, _args: &[i16]) -> Result<i16> {
+    vm.halt();
+    Ok(0)
+}
+
+fn sys_error(_vm: &mut Vm, args: &[i16]) -> Result<i16> {
+    Err(anyhow!("Sys.error : error code {}", arg(args, 0)?))
+}
@@ -0,0 +1,107 @@
+use vmtranslator::parser::{Command, ParseResult, Segment};
+
+use anyhow::{anyhow, Result};
+
+use std::collections::HashMap;
+
+// first RAM address of static variables (same as hackasm variables)
+pub const STATIC_BASE: usize = 16;
+pub const STATIC_LIMIT: usize = 256;
+
+// all commands of the vm files, flatten into one instruction list
+pub struct Program {
+    pub commands: Vec<Command>,
+    functions: HashMap<String, usize>,
+    labels: HashMap<String, usize>,
+    statics: HashMap<(String, i64), usize>,
+}
+
+impl Program {
+    pub fn new(results: Vec<ParseResult>) -> Result<Program> {
+        let errors = results
+            .iter()
+            .flat_map(|res| res.errors.iter())
+            .map(|err| format!("  {:?}", err))
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Err(anyhow!("parse error: \n{}", errors.join("\n")));
+        }
+
+        let commands: Vec<Command> = results.into_iter().flat_map(|res| res.commands).collect();
+
+        let mut program = Program {
+            commands,
+            functions: HashMap::new(),
+            labels: HashMap::new(),
+            statics: HashMap::new(),
+        };
+        program.collect_symbols()?;
+
+        Ok(program)
+    }
+
+    fn collect_symbols(&mut self) -> Result<()> {
+        for (index, cmd) in self.commands.iter().enumerate() {
+            match cmd {
+                Command::Function(name, _, source) if self.functions.contains_key(name) => {
+                    return Err(anyhow!("{:?} : duplicate function definition : {}", source, name));
+                }
+                Command::Function(name, ..) => {
+                    self.functions.insert(name.clone(), index);
+                }
+                Command::Label(label, source) if self.labels.contains_key(label) => {
+                    return Err(anyhow!("{:?} : duplicate label definition : {}", source, label));
+                }
+                Command::Label(label, _) => {
+                    self.labels.insert(label.clone(), index);
+                }
+                // assign static variables in order of appearance, like hackasm does for `@Foo.0`
+                Command::Push(Segment::Static, index, source) | Command::Pop(Segment::Static, index, source) => {
+                    let key = (source.vm_name.clone(), *index);
+                    if !self.statics.contains_key(&key) {
+                        let addr = STATIC_BASE + self.statics.len();
+                        if addr >= STATIC_LIMIT {
+                            return Err(anyhow!("{:?} : too many static variables", source));
+                        }
+                        self.statics.insert(key, addr);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Command> {
+        self.commands.get(index)
+    }
+
+    pub fn function(&self, name: &str) -> Option<usize> {
+        self.functions.get(name).copied()
+    }
+
+    pub fn label(&self, label: &str) -> Option<usize> {
+        self.labels.get(label).copied()
+    }
+
+    pub fn static_addr(&self, vm_name: &str, index: i64) -> Option<usize> {
+        self.statics.get(&(vm_name.to_string(), index)).copied()
+    }
+
+    // name of the function that contains the command at `index`
+    pub fn function_at(&self, index: usize) -> Option<&str> {
+        self.commands.get(..=index)?.iter().rev().find_map(|cmd| match cmd {
+            Command::Function(name, ..) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+}
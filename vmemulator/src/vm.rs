@@ -0,0 +1,324 @@
+use crate::os::{self, Os};
+use crate::program::Program;
+
+use cpuemulator::Memory;
+use vmtranslator::parser::{Command, ParseResult, Segment, Source};
+
+use anyhow::{anyhow, Result};
+
+use std::collections::HashMap;
+
+// virtual registers
+pub const SP: usize = 0;
+pub const LCL: usize = 1;
+pub const ARG: usize = 2;
+pub const THIS: usize = 3;
+pub const THAT: usize = 4;
+pub const TEMP: usize = 5;
+
+// stack base address, same as `codegen::gen_prelude`
+pub const STACK_BASE: u16 = 256;
+
+// natively implemented function, called with its arguments and returns the value to push
+pub type NativeFn = fn(&mut Vm, &[i16]) -> Result<i16>;
+
+pub struct Vm {
+    pub ram: Memory,
+    pub program: Program,
+    pub pc: usize,
+    pub steps: usize, // number of executed commands
+    pub os: Os,
+    natives: HashMap<String, NativeFn>,
+    tail_call: Option<&'static str>, // function called in place of the returning native
+    halted: bool,
+}
+
+impl Vm {
+    pub fn new(program: Program) -> Vm {
+        let mut vm = Vm {
+            ram: Memory::new(),
+            program,
+            pc: 0,
+            steps: 0,
+            os: Os::new(),
+            natives: HashMap::new(),
+            tail_call: None,
+            halted: false,
+        };
+
+        os::install(&mut vm);
+
+        // without Sys.init in the program, the native Sys.init calls Main.main
+        if vm.program.function("Sys.init").is_some() || vm.program.function("Main.main").is_some() {
+            vm.bootstrap().expect("bootstrap can not fail on fresh RAM");
+        } else {
+            vm.skip_labels();
+        }
+
+        vm
+    }
+
+    pub fn from_results(results: Vec<ParseResult>) -> Result<Vm> {
+        Ok(Vm::new(Program::new(results)?))
+    }
+
+    // implement `name` in rust, used only if no vm file defines the function
+    pub fn register_native(&mut self, name: &str, f: NativeFn) {
+        self.natives.insert(name.to_string(), f);
+    }
+
+    // SP = 256, call Sys.init
+    pub fn bootstrap(&mut self) -> Result<()> {
+        self.set_reg(SP, STACK_BASE)?;
+
+        // returning from Sys.init halts the vm
+        let retaddr = self.program.len();
+        self.call("Sys.init", 0, retaddr, None)
+    }
+
+    // make the running native continue in `name`, which returns to the caller of the native
+    pub fn tail_call(&mut self, name: &'static str) {
+        self.tail_call = Some(name);
+    }
+
+    pub fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted || self.pc >= self.program.len() || self.is_halt_loop()
+    }
+
+    // true if the vm is spinning in `label WHILE; goto WHILE`
+    fn is_halt_loop(&self) -> bool {
+        match self.program.get(self.pc) {
+            Some(Command::Goto(label, _)) => self.program.label(label).is_some_and(|idx| self.next_command(idx) == self.pc),
+            _ => false,
+        }
+    }
+
+    pub fn current_function(&self) -> Option<&str> {
+        self.program.function_at(self.pc)
+    }
+
+    // execute until `pred` returns true or `max_steps` commands were executed.
+    // returns the number of executed commands
+    pub fn run_until<F>(&mut self, max_steps: usize, mut pred: F) -> Result<usize>
+    where
+        F: FnMut(&Vm) -> bool,
+    {
+        let mut steps = 0;
+        while steps < max_steps && !pred(self) {
+            self.step()?;
+            steps += 1;
+        }
+        Ok(steps)
+    }
+
+    pub fn run(&mut self, max_steps: usize) -> Result<usize> {
+        self.run_until(max_steps, |vm| vm.is_halted())
+    }
+
+    // execute one vm command
+    pub fn step(&mut self) -> Result<()> {
+        let cmd = match self.program.get(self.pc) {
+            Some(cmd) if !self.halted => cmd.clone(),
+            _ => return Ok(()),
+        };
+        self.pc += 1;
+
+        match cmd {
+            // arithmetic commands
+            Command::Add(_) => self.binary(|x, y| x.wrapping_add(y))?,
+            Command::Sub(_) => self.binary(|x, y| x.wrapping_sub(y))?,
+            Command::Neg(_) => self.unary(|x| x.wrapping_neg())?,
+            Command::Eq(_) => self.binary(|x, y| to_bool(x == y))?,
+            Command::Gt(_) => self.binary(|x, y| to_bool(x > y))?,
+            Command::Lt(_) => self.binary(|x, y| to_bool(x < y))?,
+            Command::And(_) => self.binary(|x, y| x & y)?,
+            Command::Or(_) => self.binary(|x, y| x | y)?,
+            Command::Not(_) => self.unary(|x| !x)?,
+            // memory access commands
+            Command::Push(segment, index, source) => self.exec_push(&segment, index, &source)?,
+            Command::Pop(segment, index, source) => self.exec_pop(&segment, index, &source)?,
+            // program flow commands
+            Command::Label(..) => {}
+            Command::Goto(label, source) => self.jump(&label, &source)?,
+            Command::IfGoto(label, source) => self.exec_if_goto(&label, &source)?,
+            // function commands
+            Command::Function(_, nlocals, _) => (0..nlocals).try_for_each(|_| self.push(0))?,
+            Command::Call(name, arity, source) => self.call(&name, arity, self.pc, Some(&source))?,
+            Command::Return(_) => self.exec_return()?,
+        }
+
+        self.steps += 1;
+        self.skip_labels();
+
+        Ok(())
+    }
+
+    fn exec_push(&mut self, segment: &Segment, index: i64, source: &Source) -> Result<()> {
+        let value = if *segment == Segment::Constant {
+            index as i16
+        } else {
+            let addr = self.segment_addr(segment, index, source)?;
+            self.read_ram(addr)?
+        };
+        self.push(value)
+    }
+
+    fn exec_pop(&mut self, segment: &Segment, index: i64, source: &Source) -> Result<()> {
+        let addr = self.segment_addr(segment, index, source)?;
+        let value = self.pop()?;
+        self.write_ram(addr, value)
+    }
+
+    fn segment_addr(&self, segment: &Segment, index: i64, source: &Source) -> Result<usize> {
+        let base = match segment {
+            Segment::Local => self.reg(LCL)? as i64,
+            Segment::Argument => self.reg(ARG)? as i64,
+            Segment::This => self.reg(THIS)? as i64,
+            Segment::That => self.reg(THAT)? as i64,
+            Segment::Pointer => THIS as i64,
+            Segment::Temp => TEMP as i64,
+            Segment::Static => {
+                return self
+                    .program
+                    .static_addr(&source.vm_name, index)
+                    .ok_or_else(|| anyhow!("{:?} : unknown static variable : {}", source, index))
+            }
+            Segment::Constant => return Err(anyhow!("{:?} : constant segment has no address", source)),
+        };
+
+        Ok((base + index) as usize)
+    }
+
+    fn exec_if_goto(&mut self, label: &str, source: &Source) -> Result<()> {
+        if self.pop()? != 0 {
+            self.jump(label, source)?;
+        }
+        Ok(())
+    }
+
+    fn jump(&mut self, label: &str, source: &Source) -> Result<()> {
+        self.pc = self.program.label(label).ok_or_else(|| anyhow!("{:?} : undefined label : {}", source, label))?;
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, arity: i64, retaddr: usize, source: Option<&Source>) -> Result<()> {
+        if let Some(addr) = self.program.function(name) {
+            // push return address and caller's frame
+            self.push(retaddr as u16 as i16)?;
+            for reg in &[LCL, ARG, THIS, THAT] {
+                let value = self.reg(*reg)?;
+                self.push(value as i16)?;
+            }
+
+            let sp = self.reg(SP)?;
+            self.set_reg(ARG, sp.wrapping_sub(5 + arity as u16))?;
+            self.set_reg(LCL, sp)?;
+            self.pc = addr;
+
+            return Ok(());
+        }
+
+        let native = self
+            .natives
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("{:?} : undefined function : {}", source, name))?;
+
+        let mut args = (0..arity).map(|_| self.pop()).collect::<Result<Vec<_>>>()?;
+        args.reverse();
+
+        let value = native(self, &args).map_err(|err| anyhow!("{:?} : {} : {}", source, name, err))?;
+        if let Some(callee) = self.tail_call.take() {
+            return self.call(callee, 0, retaddr, source);
+        }
+
+        self.push(value)?;
+        self.pc = retaddr;
+
+        Ok(())
+    }
+
+    fn exec_return(&mut self) -> Result<()> {
+        let frame = self.reg(LCL)? as usize;
+        let retaddr = self.read_ram(frame.wrapping_sub(5))? as u16 as usize;
+
+        // return value to *ARG, restore SP
+        let value = self.pop()?;
+        let arg = self.reg(ARG)?;
+        self.write_ram(arg as usize, value)?;
+        self.set_reg(SP, arg.wrapping_add(1))?;
+
+        // restore caller's frame
+        for (offset, reg) in [THAT, THIS, ARG, LCL].iter().enumerate() {
+            let value = self.read_ram(frame.wrapping_sub(offset + 1))?;
+            self.set_reg(*reg, value as u16)?;
+        }
+
+        self.pc = retaddr;
+        Ok(())
+    }
+
+    fn binary<F: Fn(i16, i16) -> i16>(&mut self, f: F) -> Result<()> {
+        let y = self.pop()?;
+        let x = self.pop()?;
+        self.push(f(x, y))
+    }
+
+    fn unary<F: Fn(i16) -> i16>(&mut self, f: F) -> Result<()> {
+        let x = self.pop()?;
+        self.push(f(x))
+    }
+
+    // labels are not commands to step on
+    fn skip_labels(&mut self) {
+        self.pc = self.next_command(self.pc);
+    }
+
+    fn next_command(&self, index: usize) -> usize {
+        let mut index = index;
+        while let Some(Command::Label(..)) = self.program.get(index) {
+            index += 1;
+        }
+        index
+    }
+
+    pub fn push(&mut self, value: i16) -> Result<()> {
+        let sp = self.reg(SP)?;
+        self.write_ram(sp as usize, value)?;
+        self.set_reg(SP, sp.wrapping_add(1))
+    }
+
+    pub fn pop(&mut self) -> Result<i16> {
+        let sp = self.reg(SP)?.wrapping_sub(1);
+        self.set_reg(SP, sp)?;
+        self.read_ram(sp as usize)
+    }
+
+    pub fn reg(&self, reg: usize) -> Result<u16> {
+        self.ram.read(reg)
+    }
+
+    pub fn set_reg(&mut self, reg: usize, value: u16) -> Result<()> {
+        self.ram.write(reg, value)
+    }
+
+    pub fn read_ram(&self, addr: usize) -> Result<i16> {
+        self.ram.read(addr).map(|v| v as i16)
+    }
+
+    pub fn write_ram(&mut self, addr: usize, value: i16) -> Result<()> {
+        self.ram.write(addr, value as u16)
+    }
+}
+
+fn to_bool(b: bool) -> i16 {
+    if b {
+        -1
+    } else {
+        0
+    }
+}
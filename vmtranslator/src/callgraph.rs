@@ -0,0 +1,121 @@
+// call graph across vm files and elimination of functions unreachable from Sys.init
+//
+// commands from `function f` up to the next `function` are the body of f,
+// commands before the first `function` of a file are always kept
+use crate::parser::{Command, ParseResult};
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+const ENTRY_POINT: &str = "Sys.init";
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub removed: Vec<(String, usize)>, // function name and number of commands
+    pub before: usize,                 // number of commands
+    pub after: usize,
+}
+
+// callees of each function
+pub fn build(results: &[ParseResult]) -> HashMap<String, Vec<String>> {
+    let mut graph: HashMap<String, Vec<String>> = HashMap::new();
+
+    results.iter().for_each(|res| {
+        let mut current: Option<&str> = None;
+        res.commands.iter().for_each(|cmd| match cmd {
+            Command::Function(name, ..) => {
+                graph.entry(name.clone()).or_default();
+                current = Some(name);
+            }
+            Command::Call(callee, ..) => {
+                if let Some(caller) = current {
+                    graph.entry(caller.to_string()).or_default().push(callee.clone());
+                }
+            }
+            _ => {}
+        });
+    });
+
+    graph
+}
+
+// functions reachable from Sys.init
+pub fn reachable(graph: &HashMap<String, Vec<String>>) -> HashSet<String> {
+    let mut visited = HashSet::new();
+    let mut stack = vec![ENTRY_POINT.to_string()];
+
+    while let Some(name) = stack.pop() {
+        if !visited.insert(name.clone()) {
+            continue;
+        }
+        if let Some(callees) = graph.get(&name) {
+            stack.extend(callees.iter().filter(|callee| !visited.contains(*callee)).cloned());
+        }
+    }
+
+    visited
+}
+
+// drop unreachable functions, nothing is dropped without Sys.init
+pub fn eliminate(results: Vec<ParseResult>) -> (Vec<ParseResult>, Report) {
+    let before: usize = results.iter().map(|res| res.commands.len()).sum();
+
+    let graph = build(&results);
+    if !graph.contains_key(ENTRY_POINT) {
+        let report = Report {
+            removed: vec![],
+            before,
+            after: before,
+        };
+        return (results, report);
+    }
+
+    let live = reachable(&graph);
+    let mut removed = Vec::new();
+
+    let results: Vec<ParseResult> = results
+        .into_iter()
+        .map(|res| {
+            let mut commands = Vec::with_capacity(res.commands.len());
+            let mut dead: Option<(String, usize)> = None;
+
+            res.commands.into_iter().for_each(|cmd| {
+                if let Command::Function(name, ..) = &cmd {
+                    removed.extend(dead.take());
+                    if !live.contains(name) {
+                        dead = Some((name.clone(), 0));
+                    }
+                }
+
+                match dead.as_mut() {
+                    Some((_, count)) => *count += 1,
+                    None => commands.push(cmd),
+                }
+            });
+            removed.extend(dead);
+
+            ParseResult { commands, ..res }
+        })
+        .collect();
+
+    let after = results.iter().map(|res| res.commands.len()).sum();
+    (results, Report { removed, before, after })
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let saved = self.before - self.after;
+        let ratio = if self.before > 0 { saved as f64 * 100.0 / self.before as f64 } else { 0.0 };
+
+        write!(
+            f,
+            "removed {} unreachable functions, {} -> {} commands ({} saved, {:.1}%)",
+            self.removed.len(),
+            self.before,
+            self.after,
+            saved,
+            ratio
+        )?;
+        self.removed.iter().try_for_each(|(name, count)| write!(f, "\n  {} ({} commands)", name, count))
+    }
+}
@@ -8,7 +8,7 @@ use anyhow::Result;
 
 pub fn gen_function_def(_vm_name: &str, name: &str, nlocals: i64, _source: Source) -> Result<String> {
     let push = gen_stack_push()?;
-    let locals = iter::repeat(push).take(nlocals as usize).collect::<Vec<String>>().join("\n");
+    let locals = iter::repeat_n(push, nlocals as usize).collect::<Vec<String>>().join("\n");
 
     Ok(format!(
         r#"
@@ -83,8 +83,12 @@ M=D
 }
 
 pub fn gen_return(_vm_name: &str, _source: Source) -> Result<String> {
-    let asm = r#"
-// return
+    Ok(format!("\n// return{}", gen_return_body()))
+}
+
+// restore the caller's frame and jump to the return address
+pub fn gen_return_body() -> &'static str {
+    r#"
 @LCL    // R13(FRAME) = LCL
 D=M
 @R13
@@ -146,7 +150,5 @@ M=D
 @R14    // jump to return address
 A=M
 0;JEQ
-"#;
-
-    Ok(asm.to_string())
+"#
 }
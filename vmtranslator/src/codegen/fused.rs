@@ -0,0 +1,77 @@
+use crate::codegen::segment::{gen_segment_read, gen_segment_write};
+use crate::codegen::stack::gen_stack_pop;
+use crate::parser::Command;
+
+use anyhow::Result;
+
+// superinstructions, a command pair compiled without going through the stack
+//
+//   push x, add|sub|and|or     x is read into D-register and applied to the stack top
+//   push x, pop y              x is moved to y by D-register
+//   eq|gt|lt, if-goto L        jump by the difference, true/false is not pushed
+//
+// returns the number of commands consumed, `None` if no pair matches
+pub fn gen_fused(vm_name: &str, cmds: &[Command]) -> Option<(usize, Result<String>)> {
+    match cmds {
+        [Command::Push(seg, index, _), op @ (Command::Add(_) | Command::Sub(_) | Command::And(_) | Command::Or(_)), ..] => {
+            let (name, comp) = match op {
+                Command::Add(_) => ("add", "D+M"),
+                Command::Sub(_) => ("sub", "M-D"),
+                Command::And(_) => ("and", "D&M"),
+                _ => ("or", "D|M"),
+            };
+            let asm = format!(
+                r#"// push {:?} {}, {}
+{}
+@SP    // replace stack top by {}
+A=M-1
+M={}"#,
+                seg,
+                index,
+                name,
+                gen_segment_read(vm_name, seg.clone(), *index),
+                comp,
+                comp
+            );
+
+            Some((2, Ok(asm)))
+        }
+
+        [Command::Push(seg, index, _), Command::Pop(pop_seg, pop_index, _), ..] => {
+            let read = gen_segment_read(vm_name, seg.clone(), *index);
+            let asm = gen_segment_write(vm_name, pop_seg.clone(), *pop_index, &read)
+                .map(|write| format!("// push {:?} {}, pop {:?} {}\n{}", seg, index, pop_seg, pop_index, write));
+
+            Some((2, asm))
+        }
+
+        [cmp @ (Command::Eq(_) | Command::Gt(_) | Command::Lt(_)), Command::IfGoto(label, _), ..] => {
+            let (name, jump) = match cmp {
+                Command::Eq(_) => ("eq", "JEQ"),
+                Command::Gt(_) => ("gt", "JGT"),
+                _ => ("lt", "JLT"),
+            };
+
+            Some((2, gen_compare_jump(name, jump, label)))
+        }
+
+        _ => None,
+    }
+}
+
+fn gen_compare_jump(name: &str, jump: &str, label: &str) -> Result<String> {
+    let pop = gen_stack_pop()?;
+
+    Ok(format!(
+        r#"
+// {}, if-goto {}
+{}
+@SP    // pop stack and compare
+AM=M-1
+D=M-D
+@{} // set destination label
+D;{}
+"#,
+        name, label, pop, label, jump
+    ))
+}
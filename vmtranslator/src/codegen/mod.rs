@@ -1,16 +1,24 @@
 pub mod arithmetic;
 pub mod flow;
 pub mod function;
+pub mod fused;
+pub mod runtime;
 pub mod segment;
 pub mod stack;
 
+use crate::codegen::runtime::Runtime;
 use crate::parser::{Command, ParseResult};
 
 use std::collections::HashMap;
 
 use anyhow::{Error, Result};
 
-pub fn generate(results: Vec<ParseResult>) -> (String, Vec<Error>) {
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    pub shared: bool, // jump into shared call/return and comparison routines
+}
+
+pub fn generate(results: Vec<ParseResult>, options: &Options) -> (String, Vec<Error>) {
     // search Sys.init
     let has_sys_init = results.iter().any(|res| {
         res.commands.iter().any(|cmd| match cmd {
@@ -23,7 +31,7 @@ pub fn generate(results: Vec<ParseResult>) -> (String, Vec<Error>) {
 
     // generate prelude to call Sys.init
     let prelude = if has_sys_init {
-        match gen_prelude(&mut table) {
+        match gen_prelude(&mut table, options) {
             Ok(prelude) => prelude,
             Err(err) => return ("".to_string(), vec![err]),
         }
@@ -31,20 +39,36 @@ pub fn generate(results: Vec<ParseResult>) -> (String, Vec<Error>) {
         "".to_string()
     };
 
+    // shared routines used by the program
+    let runtime = if options.shared {
+        let mut used = Runtime::used(&results);
+        used.call |= has_sys_init;
+        match runtime::gen_runtime(used) {
+            Ok(runtime) => runtime,
+            Err(err) => return ("".to_string(), vec![err]),
+        }
+    } else {
+        "".to_string()
+    };
+
     let (codes, errors): (Vec<_>, Vec<_>) = results
         .into_iter()
-        .flat_map(|res| gen(&res.vm_name, res.commands, &mut table))
+        .flat_map(|res| gen(&res.vm_name, res.commands, &mut table, options))
         .partition(Result::is_ok);
 
     let codes: Vec<String> = codes.into_iter().map(|res| res.unwrap()).collect();
     let errors: Vec<Error> = errors.into_iter().map(|res| res.unwrap_err()).collect();
 
-    let asm = format!("{}\n\n{}", prelude, codes.join("\n\n"));
+    let asm = format!("{}{}\n\n{}", prelude, runtime, codes.join("\n\n"));
     (asm, errors)
 }
 
-pub fn gen_prelude(table: &mut LabelTable) -> Result<String> {
-    let call_sys_init = function::gen_call("prelude", "Sys.init", 0, table, None)?;
+pub fn gen_prelude(table: &mut LabelTable, options: &Options) -> Result<String> {
+    let call_sys_init = if options.shared {
+        runtime::gen_call("prelude", "Sys.init", 0, table, None)?
+    } else {
+        function::gen_call("prelude", "Sys.init", 0, table, None)?
+    };
 
     // initialize stack pointer
     Ok(format!(
@@ -64,11 +88,32 @@ M=D
 
 pub type LabelTable = HashMap<String, i64>;
 
-fn gen(vm_name: &str, commands: Vec<Command>, table: &mut LabelTable) -> Vec<Result<String>> {
-    commands.into_iter().map(|cmd| gen_cmd(vm_name, cmd, table)).collect::<Vec<_>>()
+fn gen(vm_name: &str, commands: Vec<Command>, table: &mut LabelTable, options: &Options) -> Vec<Result<String>> {
+    let mut codes = Vec::with_capacity(commands.len());
+    let mut rest = commands.as_slice();
+
+    // fused pairs first, or each command in isolation
+    while let Some(cmd) = rest.first() {
+        let (consumed, code) = fused::gen_fused(vm_name, rest).unwrap_or_else(|| (1, gen_cmd(vm_name, cmd.clone(), table, options)));
+        codes.push(code);
+        rest = &rest[consumed..];
+    }
+
+    codes
 }
 
-fn gen_cmd(vm_name: &str, cmd: Command, table: &mut LabelTable) -> Result<String> {
+fn gen_cmd(vm_name: &str, cmd: Command, table: &mut LabelTable, options: &Options) -> Result<String> {
+    if options.shared {
+        match cmd {
+            Command::Eq(source) => return runtime::gen_compare("EQ", table, source),
+            Command::Gt(source) => return runtime::gen_compare("GT", table, source),
+            Command::Lt(source) => return runtime::gen_compare("LT", table, source),
+            Command::Call(name, arity, source) => return runtime::gen_call(vm_name, &name, arity, table, Some(source)),
+            Command::Return(source) => return runtime::gen_return(vm_name, source),
+            _ => {}
+        }
+    }
+
     match cmd {
         // arithmetic commands
         Command::Add(source) => arithmetic::gen_add(source),
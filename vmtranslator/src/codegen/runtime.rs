@@ -0,0 +1,214 @@
+use crate::codegen::stack::{gen_stack_pop, gen_stack_push};
+use crate::codegen::{fused, gen_new_label, LabelTable};
+use crate::parser::{Command, ParseResult, Source};
+
+use anyhow::Result;
+
+// shared routines emitted once, each call site jumps into them
+//
+//   $$CALL    R13 = arity + 5, R14 = return address, R15 = callee
+//   $$RETURN  restores the caller's frame
+//   $$EQ, $$GT, $$LT  R13 = return address
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Runtime {
+    pub call: bool,
+    pub ret: bool,
+    pub eq: bool,
+    pub gt: bool,
+    pub lt: bool,
+}
+
+impl Runtime {
+    // routines used by the commands, pairs fused by codegen do not use them
+    pub fn used(results: &[ParseResult]) -> Runtime {
+        let mut runtime = Runtime::default();
+
+        results.iter().for_each(|res| {
+            let mut rest = res.commands.as_slice();
+            while let Some(cmd) = rest.first() {
+                if let Some((consumed, _)) = fused::gen_fused(&res.vm_name, rest) {
+                    rest = &rest[consumed..];
+                    continue;
+                }
+
+                match cmd {
+                    Command::Call(..) => runtime.call = true,
+                    Command::Return(..) => runtime.ret = true,
+                    Command::Eq(..) => runtime.eq = true,
+                    Command::Gt(..) => runtime.gt = true,
+                    Command::Lt(..) => runtime.lt = true,
+                    _ => {}
+                }
+                rest = &rest[1..];
+            }
+        });
+
+        runtime
+    }
+}
+
+// routines placed before the program, jumped over at start
+pub fn gen_runtime(runtime: Runtime) -> Result<String> {
+    let mut routines = Vec::new();
+
+    if runtime.call {
+        routines.push(gen_call_routine()?);
+    }
+    if runtime.ret {
+        routines.push(gen_return_routine()?);
+    }
+    if runtime.eq {
+        routines.push(gen_compare_routine("EQ", "JEQ")?);
+    }
+    if runtime.gt {
+        routines.push(gen_compare_routine("GT", "JGT")?);
+    }
+    if runtime.lt {
+        routines.push(gen_compare_routine("LT", "JLT")?);
+    }
+
+    if routines.is_empty() {
+        return Ok("".to_string());
+    }
+
+    Ok(format!(
+        r#"
+// shared runtime
+@$$START
+0;JEQ
+{}
+($$START)
+"#,
+        routines.join("\n")
+    ))
+}
+
+pub fn gen_call(_vm_name: &str, name: &str, arity: i64, table: &mut LabelTable, _source: Option<Source>) -> Result<String> {
+    let retaddr = gen_new_label("RET_ADDR_CALL", table);
+
+    Ok(format!(
+        r#"
+// call {} {}
+@{}     // R14 = return address
+D=A
+@R14
+M=D
+@{}     // R13 = arity + 5
+D=A
+@R13
+M=D
+@{}     // R15 = callee
+D=A
+@R15
+M=D
+@$$CALL
+0;JEQ
+({})
+"#,
+        name,
+        arity,
+        retaddr,
+        arity + 5,
+        name,
+        retaddr
+    ))
+}
+
+pub fn gen_return(_vm_name: &str, _source: Source) -> Result<String> {
+    Ok(r#"
+// return
+@$$RETURN
+0;JEQ
+"#
+    .to_string())
+}
+
+pub fn gen_compare(op: &str, table: &mut LabelTable, _source: Source) -> Result<String> {
+    let retaddr = gen_new_label(&format!("RET_ADDR_{}", op), table);
+
+    Ok(format!(
+        r#"
+// {}
+@{}     // R13 = return address
+D=A
+@R13
+M=D
+@$${}
+0;JEQ
+({})
+"#,
+        op.to_lowercase(),
+        retaddr,
+        op,
+        retaddr
+    ))
+}
+
+fn gen_call_routine() -> Result<String> {
+    let push = gen_stack_push()?;
+
+    Ok(format!(
+        r#"
+($$CALL)
+@R14    // push return address
+D=M
+{}
+@LCL    // push local segment pointer
+D=M
+{}
+@ARG    // push argument segment pointer
+D=M
+{}
+@THIS   // push this segment pointer
+D=M
+{}
+@THAT   // push that segment pointer
+D=M
+{}
+@R13    // ARG = SP - (arity + 5)
+D=M
+@SP
+D=M-D
+@ARG
+M=D
+@SP     // LCL = SP
+D=M
+@LCL
+M=D
+@R15    // jump to the callee
+A=M
+0;JEQ
+"#,
+        push, push, push, push, push
+    ))
+}
+
+fn gen_return_routine() -> Result<String> {
+    let ret = crate::codegen::function::gen_return_body();
+
+    Ok(format!("\n($$RETURN){}", ret))
+}
+
+fn gen_compare_routine(op: &str, jump: &str) -> Result<String> {
+    let pop = gen_stack_pop()?;
+
+    Ok(format!(
+        r#"
+($${})
+{}
+A=A-1
+D=M-D
+M=-1 // set true to stack top
+@$${}_TRUE
+D;{}
+@SP
+A=M-1
+M=0 // set false to stack top
+($${}_TRUE)
+@R13    // jump to return address
+A=M
+0;JEQ
+"#,
+        op, pop, op, jump, op
+    ))
+}
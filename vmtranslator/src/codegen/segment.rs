@@ -1,5 +1,7 @@
 use crate::parser::Segment;
 
+use anyhow::{anyhow, Result};
+
 pub fn gen_segment_read(vm_name: &str, seg: Segment, index: i64) -> String {
     match seg {
         Segment::Constant => gen_segment_read_constant(index),
@@ -89,25 +91,24 @@ D=M
     .to_string()
 }
 
-pub fn gen_segment_write(vm_name: &str, seg: Segment, index: i64) -> String {
-    match seg {
-        Segment::Local => gen_segment_write_local(index),
-        Segment::Argument => gen_segment_write_argument(index),
-        Segment::Pointer => gen_segment_write_pointer(index),
-        Segment::This => gen_segment_write_this(index),
-        Segment::That => gen_segment_write_that(index),
-        Segment::Temp => gen_segment_write_temp(index),
-        Segment::Static => gen_segment_write_static(vm_name, index),
-        _ => String::from(""), // TODO
-    }
-}
+// beyond this index, the address is calculated into R13 instead of repeating `A=A+1`
+// R13 costs 9 words (`@i D=A @SEG D=D+M @R13 M=D`, `@R13 A=M M=D`), `A=A+1`s cost 3 + index
+const INDEX_WRITE_THRESHOLD: i64 = 6;
 
-fn gen_segment_write_local(index: i64) -> String {
-    gen_segment_index_write("local", "LCL", index)
-}
+// write D-register value set by `value` (stack pop or segment read) to the segment
+pub fn gen_segment_write(vm_name: &str, seg: Segment, index: i64, value: &str) -> Result<String> {
+    let asm = match seg {
+        Segment::Local => gen_segment_index_write("local", "LCL", index, value),
+        Segment::Argument => gen_segment_index_write("argument", "ARG", index, value),
+        Segment::This => gen_segment_index_write("this", "THIS", index, value),
+        Segment::That => gen_segment_index_write("that", "THAT", index, value),
+        Segment::Pointer => format!("{}\n{}", value, gen_segment_write_pointer(index)),
+        Segment::Temp => format!("{}\n{}", value, gen_segment_write_temp(index)),
+        Segment::Static => format!("{}\n{}", value, gen_segment_write_static(vm_name, index)),
+        Segment::Constant => return Err(anyhow!("codegen: can not write to constant segment : {}", index)),
+    };
 
-fn gen_segment_write_argument(index: i64) -> String {
-    gen_segment_index_write("argument", "ARG", index)
+    Ok(asm)
 }
 
 fn gen_segment_write_pointer(index: i64) -> String {
@@ -121,14 +122,6 @@ M=D
     .to_string()
 }
 
-fn gen_segment_write_this(index: i64) -> String {
-    gen_segment_index_write("this", "THIS", index)
-}
-
-fn gen_segment_write_that(index: i64) -> String {
-    gen_segment_index_write("that", "THAT", index)
-}
-
 fn gen_segment_write_temp(index: i64) -> String {
     let reg = 5 + index;
     format!(
@@ -140,15 +133,33 @@ M=D
     .to_string()
 }
 
-fn gen_segment_index_write(name: &str, segment: &str, index: i64) -> String {
+fn gen_segment_index_write(name: &str, segment: &str, index: i64, value: &str) -> String {
+    if index > INDEX_WRITE_THRESHOLD {
+        return format!(
+            r#"@{} // set address of ({} {}) to R13
+D=A
+@{}
+D=D+M
+@R13
+M=D
+{}
+@R13   // write d-register value to ({} {})
+A=M
+M=D
+"#,
+            index, name, index, segment, value, name, index
+        );
+    }
+
     let incr = "A=A+1\n".repeat(index as usize);
     format!(
-        r#"@{} // write d-register value to ({} {})
+        r#"{}
+@{} // write d-register value to ({} {})
 A=M
 {}
 M=D
 "#,
-        segment, name, index, incr
+        value, segment, name, index, incr
     )
     .to_string()
 }
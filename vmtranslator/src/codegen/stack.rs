@@ -5,7 +5,7 @@ use crate::parser::Source;
 use anyhow::Result;
 
 pub fn gen_push(vm_name: &str, seg: Segment, index: i64, _source: Source) -> Result<String> {
-    let asm = vec![format!("// push {:?} {}", seg, index), gen_segment_read(vm_name, seg, index), gen_stack_push()?].join("\n");
+    let asm = [format!("// push {:?} {}", seg, index), gen_segment_read(vm_name, seg, index), gen_stack_push()?].join("\n");
 
     Ok(asm)
 }
@@ -33,7 +33,8 @@ D=M
 }
 
 pub fn gen_pop(vm_name: &str, seg: Segment, index: i64, _source: Source) -> Result<String> {
-    let asm = vec![format!("// pop {:?} {}", seg, index), gen_stack_pop()?, gen_segment_write(vm_name, seg, index)].join("\n");
+    let comment = format!("// pop {:?} {}", seg, index);
+    let asm = [comment, gen_segment_write(vm_name, seg, index, &gen_stack_pop()?)?].join("\n");
 
     Ok(asm)
 }
@@ -1,4 +1,6 @@
+pub mod callgraph;
 pub mod codegen;
+pub mod optimizer;
 pub mod parser;
 pub mod source;
 
@@ -6,6 +8,8 @@ use crate::codegen::*;
 use crate::parser::*;
 use crate::source::*;
 
+use getopts::Options;
+
 use std::env;
 use std::process;
 
@@ -13,15 +17,25 @@ use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
+struct Config {
+    target: String, // *.vm file or directory
+    options: codegen::Options,
+    optimize: bool,  // optimize vm commands before codegen
+    eliminate: bool, // drop functions unreachable from Sys.init
+}
+
 /**
  * 1. Read file or directory
  * 2. parse each vm files to VMCommand(s)
- * 3. generate hack asm from VMCommand(s)
+ * 3. drop functions unreachable from Sys.init (optional)
+ * 4. optimize VMCommand(s) (optional)
+ * 5. generate hack asm from VMCommand(s)
  *
  */
 pub fn process() {
     // get filename or directory from args
-    let arg = get_first_arg();
+    let config = parse_args();
+    let arg = config.target;
     let (dir, vm_name) = parse_arg(&arg);
 
     let sources = read_sources(&arg).unwrap_or_else(|err| {
@@ -30,7 +44,7 @@ pub fn process() {
     });
 
     let results: Vec<ParseResult> = sources.map(|src| parse(&src.code, &src.vm_name)).collect();
-    let errors: Vec<&anyhow::Error> = results.iter().map(|res| &res.errors).flatten().collect();
+    let errors: Vec<&anyhow::Error> = results.iter().flat_map(|res| &res.errors).collect();
 
     if !errors.is_empty() {
         println!("parse error: ");
@@ -38,8 +52,17 @@ pub fn process() {
         process::exit(1);
     }
 
+    let results = if config.eliminate {
+        let (results, report) = callgraph::eliminate(results);
+        println!("{}", report);
+        results
+    } else {
+        results
+    };
+    let results = if config.optimize { optimize(results) } else { results };
+
     // generate code
-    let (asm, errors) = generate(results);
+    let (asm, errors) = generate(results, &config.options);
 
     if !errors.is_empty() {
         println!("codegen error: ");
@@ -54,14 +77,53 @@ pub fn process() {
     println!("{}", &asm);
 }
 
-fn get_first_arg() -> String {
-    // get first arg
+fn parse_args() -> Config {
     let args: Vec<String> = env::args().collect();
-    let name = args.get(1).unwrap_or_else(|| {
+    let mut opts = Options::new();
+
+    opts.optflag("e", "eliminate", "remove functions unreachable from Sys.init");
+    opts.optflag("O", "optimize", "fold constants, fuse push/pop, invert branches and remove dead labels");
+    opts.optflag("s", "shared", "jump into shared call/return and eq/gt/lt routines instead of inlining");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            process::exit(1);
+        }
+    };
+
+    let target = matches.free.first().cloned().unwrap_or_else(|| {
         println!("not enough arguments");
+        println!("{}", opts.usage("usage: vmtranslator [options] FILE.vm|DIR"));
         process::exit(1);
     });
-    String::from(name)
+
+    Config {
+        target,
+        options: codegen::Options {
+            shared: matches.opt_present("shared"),
+        },
+        optimize: matches.opt_present("optimize"),
+        eliminate: matches.opt_present("eliminate"),
+    }
+}
+
+fn optimize(results: Vec<ParseResult>) -> Vec<ParseResult> {
+    let before: usize = results.iter().map(|res| res.commands.len()).sum();
+
+    let results: Vec<ParseResult> = results
+        .into_iter()
+        .map(|res| ParseResult {
+            commands: optimizer::optimize(res.commands),
+            ..res
+        })
+        .collect();
+
+    let after: usize = results.iter().map(|res| res.commands.len()).sum();
+    println!("optimized: {} -> {} commands", before, after);
+
+    results
 }
 
 fn parse_arg(arg: &str) -> (String, String) {
@@ -0,0 +1,161 @@
+// optimization of vm commands before codegen
+//
+//   constant folding      push constant 2, push constant 3, add  =>  push constant 5
+//   push/pop fusion       push local 0, pop local 0              =>  (removed)
+//   branch inversion      eq, not, if-goto L                     =>  sub, if-goto L
+//                         lt, not, if-goto A, goto B, label A    =>  lt, if-goto B, label A
+//   dead label removal    labels no goto/if-goto jumps to
+//
+// patterns never span a label, so jumps into the middle of them are not affected
+use crate::parser::{Command, Segment, Source};
+
+use std::collections::HashSet;
+
+// the largest constant of `push constant`, `@N` of the hack assembly
+const MAX_CONSTANT: i64 = 0x7fff;
+
+// rewrite until nothing changes, then remove dead labels
+pub fn optimize(commands: Vec<Command>) -> Vec<Command> {
+    let mut commands = commands;
+
+    loop {
+        let (rewritten, changed) = peephole(commands);
+        commands = rewritten;
+        if !changed {
+            break;
+        }
+    }
+
+    remove_dead_labels(commands)
+}
+
+fn peephole(commands: Vec<Command>) -> (Vec<Command>, bool) {
+    let mut optimized = Vec::with_capacity(commands.len());
+    let mut changed = false;
+    let mut i = 0;
+
+    while i < commands.len() {
+        match rewrite(&commands[i..]) {
+            Some((consumed, replacement)) => {
+                optimized.extend(replacement);
+                i += consumed;
+                changed = true;
+            }
+            None => {
+                optimized.push(commands[i].clone());
+                i += 1;
+            }
+        }
+    }
+
+    (optimized, changed)
+}
+
+// the number of commands consumed and the replacement, `None` if no pattern matches
+fn rewrite(cmds: &[Command]) -> Option<(usize, Vec<Command>)> {
+    match cmds {
+        // not, not
+        [Command::Not(_), Command::Not(_), ..] => Some((2, vec![])),
+
+        // cmp, not, if-goto A, goto B, label A  =>  cmp, if-goto B, label A
+        // the result of eq/gt/lt is -1 or 0, so `not` negates the condition
+        [cmp @ (Command::Eq(_) | Command::Gt(_) | Command::Lt(_)), Command::Not(_), Command::IfGoto(a, _), Command::Goto(b, source), label @ Command::Label(l, _), ..]
+            if a == l =>
+        {
+            Some((5, vec![cmp.clone(), Command::IfGoto(b.clone(), source.clone()), label.clone()]))
+        }
+
+        // eq, not, if-goto L  =>  sub, if-goto L
+        [Command::Eq(source), Command::Not(_), if_goto @ Command::IfGoto(..), ..] => Some((3, vec![Command::Sub(source.clone()), if_goto.clone()])),
+
+        // push x, pop x
+        [Command::Push(seg, index, _), Command::Pop(pop_seg, pop_index, _), ..] if seg == pop_seg && index == pop_index => Some((2, vec![])),
+
+        // goto L, label L
+        [Command::Goto(label, _), next @ Command::Label(l, _), ..] if label == l => Some((2, vec![next.clone()])),
+
+        _ => fold(cmds),
+    }
+}
+
+// constant folding, operands are `push constant N` or `push constant N, not`
+fn fold(cmds: &[Command]) -> Option<(usize, Vec<Command>)> {
+    let (x, x_len) = constant(cmds)?;
+
+    match &cmds[x_len..] {
+        // constant condition
+        [Command::IfGoto(label, source), ..] => {
+            let jump = if x != 0 { vec![Command::Goto(label.clone(), source.clone())] } else { vec![] };
+            Some((x_len + 1, jump))
+        }
+
+        [Command::Neg(source), ..] => {
+            let folded = materialize(x.wrapping_neg(), source);
+            (folded.len() < x_len + 1).then(|| (x_len + 1, folded))
+        }
+
+        rest => {
+            let (y, y_len) = constant(rest)?;
+            let consumed = x_len + y_len + 1;
+            let value = match rest.get(y_len)? {
+                Command::Add(source) => (x.wrapping_add(y), source),
+                Command::Sub(source) => (x.wrapping_sub(y), source),
+                Command::And(source) => (x & y, source),
+                Command::Or(source) => (x | y, source),
+                Command::Eq(source) => (boolean(x == y), source),
+                // same as `D=M-D; D;JGT` of codegen, the difference wraps around
+                Command::Gt(source) => (boolean(x.wrapping_sub(y) > 0), source),
+                Command::Lt(source) => (boolean(x.wrapping_sub(y) < 0), source),
+                _ => return None,
+            };
+
+            Some((consumed, materialize(value.0, value.1)))
+        }
+    }
+}
+
+// value of the constant at the head and the number of commands
+fn constant(cmds: &[Command]) -> Option<(i16, usize)> {
+    match cmds {
+        [Command::Push(Segment::Constant, n, _), Command::Not(_), ..] if (0..=MAX_CONSTANT).contains(n) => Some((!(*n as i16), 2)),
+        [Command::Push(Segment::Constant, n, _), ..] if (0..=MAX_CONSTANT).contains(n) => Some((*n as i16, 1)),
+        _ => None,
+    }
+}
+
+// commands to push the 16-bit value
+fn materialize(value: i16, source: &Source) -> Vec<Command> {
+    if value >= 0 {
+        vec![Command::Push(Segment::Constant, value as i64, source.clone())]
+    } else {
+        vec![Command::Push(Segment::Constant, !value as i64, source.clone()), Command::Not(source.clone())]
+    }
+}
+
+// true is -1
+fn boolean(b: bool) -> i16 {
+    if b {
+        -1
+    } else {
+        0
+    }
+}
+
+// labels are qualified with the function name, so jumps are resolved in the same file
+fn remove_dead_labels(commands: Vec<Command>) -> Vec<Command> {
+    let targets: HashSet<String> = commands
+        .iter()
+        .filter_map(|cmd| match cmd {
+            Command::Goto(label, _) | Command::IfGoto(label, _) => Some(label.clone()),
+            _ => None,
+        })
+        .collect();
+
+    commands
+        .into_iter()
+        .filter(|cmd| match cmd {
+            Command::Label(label, _) => targets.contains(label),
+            _ => true,
+        })
+        .collect()
+}
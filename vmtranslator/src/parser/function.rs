@@ -26,7 +26,7 @@ fn parse_call(arg1: Option<&str>, arg2: Option<&str>, source: Source) -> Result<
 }
 
 fn parse_return(current_function: &str, source: Source) -> Result<Command> {
-    if current_function == "" {
+    if current_function.is_empty() {
         Err(anyhow!("{:?} : return : current_function is emtpy", source))
     } else {
         Ok(Command::Return(source))
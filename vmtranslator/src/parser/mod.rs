@@ -56,7 +56,7 @@ pub struct Source {
     pub code: String, // original source code
 }
 
-const COMMENT: &'static str = "//";
+const COMMENT: &str = "//";
 
 pub fn parse(content: &str, vm_name: &str) -> ParseResult {
     let sources = parse_lines(content, vm_name);
@@ -78,9 +78,9 @@ fn drop_whitespaces(content: &str) -> String {
 fn build_source(content: &str, line: usize, vm_name: &str) -> Option<Source> {
     // drop whitespace
     let code = drop_whitespaces(content);
-    let code = code.splitn(2, COMMENT).next().unwrap_or("");
+    let code = code.split(COMMENT).next().unwrap_or("");
 
-    if code.len() > 0 {
+    if !code.is_empty() {
         Some(Source {
             vm_name: vm_name.to_string(),
             line: line + 1,
@@ -123,13 +123,13 @@ fn instrument(source: Source, current_function: &str) -> Result<Command> {
     let arg2 = iter.next();
 
     // parse arithmetic
-    arithmetic::parse(&cmd, current_function, &source, arg1, arg2)
+    arithmetic::parse(cmd, current_function, &source, arg1, arg2)
         // or parse memory access
-        .or_else(|| stack::parse(&cmd, current_function, &source, arg1, arg2))
+        .or_else(|| stack::parse(cmd, current_function, &source, arg1, arg2))
         // or parse program flow
-        .or_else(|| flow::parse(&cmd, current_function, &source, arg1, arg2))
+        .or_else(|| flow::parse(cmd, current_function, &source, arg1, arg2))
         // or parse function
-        .or_else(|| function::parse(&cmd, current_function, &source, arg1, arg2))
+        .or_else(|| function::parse(cmd, current_function, &source, arg1, arg2))
         // or error!
         .unwrap_or(Err(anyhow!("{:?} : unexpected vm command : {}", &source, &cmd)))
 }
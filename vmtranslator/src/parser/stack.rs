@@ -55,9 +55,9 @@ fn validate_index(segment: &Segment, index: i64, source: &Source) -> Result<i64>
         ));
     }
 
-    if index > 2 && *segment == Segment::Pointer {
+    if index > 1 && *segment == Segment::Pointer {
         return Err(anyhow!(
-            "{:?} : illegal segment index, pointer index must be less than 2 : {:?}, {}",
+            "{:?} : illegal segment index, pointer index must be 0 or 1 : {:?}, {}",
             &source,
             segment,
             index
@@ -66,7 +66,7 @@ fn validate_index(segment: &Segment, index: i64, source: &Source) -> Result<i64>
 
     if index > 7 && *segment == Segment::Temp {
         return Err(anyhow!(
-            "{:?} : illegal segment index, temp index must be less than 7 : {:?}, {}",
+            "{:?} : illegal segment index, temp index must be between 0 and 7 : {:?}, {}",
             &source,
             segment,
             index
@@ -37,7 +37,6 @@ pub fn read_sources(arg: &str) -> Result<SourceIter> {
 
     let filenames = if metadata.is_dir() {
         fs::read_dir(arg)?
-            .into_iter()
             .flat_map(|entry| {
                 let filename = entry
                     .unwrap_or_else(|err| {
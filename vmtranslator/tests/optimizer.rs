@@ -0,0 +1,78 @@
+use vmtranslator::optimizer::optimize;
+use vmtranslator::parser::{parse, Command};
+
+// optimize the vm code and print commands back as vm code
+fn optimized(code: &str) -> Vec<String> {
+    let result = parse(code, "Test");
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+    optimize(result.commands)
+        .into_iter()
+        .map(|cmd| match cmd {
+            Command::Add(_) => "add".to_string(),
+            Command::Sub(_) => "sub".to_string(),
+            Command::Neg(_) => "neg".to_string(),
+            Command::Eq(_) => "eq".to_string(),
+            Command::Gt(_) => "gt".to_string(),
+            Command::Lt(_) => "lt".to_string(),
+            Command::And(_) => "and".to_string(),
+            Command::Or(_) => "or".to_string(),
+            Command::Not(_) => "not".to_string(),
+            Command::Push(seg, index, _) => format!("push {:?} {}", seg, index),
+            Command::Pop(seg, index, _) => format!("pop {:?} {}", seg, index),
+            Command::Label(label, _) => format!("label {}", label),
+            Command::Goto(label, _) => format!("goto {}", label),
+            Command::IfGoto(label, _) => format!("if-goto {}", label),
+            Command::Function(name, nlocals, _) => format!("function {} {}", name, nlocals),
+            Command::Call(name, arity, _) => format!("call {} {}", name, arity),
+            Command::Return(_) => "return".to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn fold_arithmetic() {
+    assert_eq!(optimized("push constant 2\npush constant 3\nadd\n"), vec!["push Constant 5"]);
+    assert_eq!(optimized("push constant 7\npush constant 9\nsub\n"), vec!["push Constant 1", "not"]);
+    assert_eq!(optimized("push constant 0\nnot\npush constant 5\nand\n"), vec!["push Constant 5"]);
+    assert_eq!(optimized("push constant 32767\npush constant 2\nadd\n"), vec!["push Constant 32766", "not"]);
+}
+
+// gt/lt compile to `D=M-D; D;JGT` and `D;JLT`, the difference wraps around
+#[test]
+fn fold_comparison_with_overflow() {
+    // 20000 - (-20001) = 40001 wraps to -25535
+    assert_eq!(optimized("push constant 20000\npush constant 20000\nnot\ngt\n"), vec!["push Constant 0"]);
+    assert_eq!(optimized("push constant 20000\npush constant 20000\nnot\nlt\n"), vec!["push Constant 0", "not"]);
+    assert_eq!(optimized("push constant 3\npush constant 5\nlt\n"), vec!["push Constant 0", "not"]);
+    assert_eq!(optimized("push constant 3\npush constant 5\ngt\n"), vec!["push Constant 0"]);
+    assert_eq!(optimized("push constant 8\npush constant 8\neq\n"), vec!["push Constant 0", "not"]);
+}
+
+#[test]
+fn fold_constant_condition() {
+    let code = "push constant 0\nnot\nif-goto L\npush constant 1\nlabel L\npush constant 0\nif-goto M\nlabel M\n";
+    assert_eq!(optimized(code), vec!["goto $L", "push Constant 1", "label $L"]);
+}
+
+#[test]
+fn fuse_push_pop() {
+    assert_eq!(
+        optimized("push local 0\npop local 0\npush local 0\npop local 1\n"),
+        vec!["push Local 0", "pop Local 1"]
+    );
+}
+
+#[test]
+fn invert_branch() {
+    assert_eq!(optimized("eq\nnot\nif-goto L\nlabel L\n"), vec!["sub", "if-goto $L", "label $L"]);
+
+    let code = "lt\nnot\nif-goto A\ngoto B\nlabel A\npush constant 1\nlabel B\n";
+    assert_eq!(optimized(code), vec!["lt", "if-goto $B", "push Constant 1", "label $B"]);
+}
+
+#[test]
+fn remove_dead_labels() {
+    let code = "label UNUSED\ngoto NEXT\nlabel NEXT\npush local 0\nlabel LOOP\ngoto LOOP\n";
+    assert_eq!(optimized(code), vec!["push Local 0", "label $LOOP", "goto $LOOP"]);
+}